@@ -0,0 +1,167 @@
+use super::{Point, Ray};
+
+/// Axis-aligned bounding box
+///
+/// Used to quickly reject rays which can not hit anything inside the box
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    min: Point,
+    max: Point,
+}
+
+impl Aabb {
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box that contains every point
+    ///
+    /// Flat dimensions are padded a bit,
+    /// so the box of an axis aligned triangle still has a volume which rays can hit
+    pub fn from_points(points: &[Point]) -> Self {
+        let mut min = points[0];
+        let mut max = points[0];
+        for p in points.iter().skip(1) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+
+        let delta = 1e-4;
+        for axis in 0..3 {
+            if max[axis] - min[axis] < delta {
+                min[axis] -= delta / 2.0;
+                max[axis] += delta / 2.0;
+            }
+        }
+        Self::new(min, max)
+    }
+
+    /// The smallest box that contains both boxes
+    pub fn surrounding_box(a: &Self, b: &Self) -> Self {
+        let min = Point::new(
+            a.min.x().min(b.min.x()),
+            a.min.y().min(b.min.y()),
+            a.min.z().min(b.min.z()),
+        );
+        let max = Point::new(
+            a.max.x().max(b.max.x()),
+            a.max.y().max(b.max.y()),
+            a.max.z().max(b.max.z()),
+        );
+        Self::new(min, max)
+    }
+
+    /// The same box moved by the offset
+    pub fn translate(&self, offset: &Point) -> Self {
+        Self::new(self.min + *offset, self.max + *offset)
+    }
+
+    pub fn min(&self) -> &Point {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Point {
+        &self.max
+    }
+
+    pub fn centroid(&self) -> Point {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Vector pointing from the min corner to the max corner
+    pub fn diagonal(&self) -> Point {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Index of the dimension where the box is the widest
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.diagonal();
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The slab method
+    ///
+    /// The ray is clipped against the two planes of each axis.
+    /// If the remaining interval is empty, then the ray missed the box.
+    pub fn hit(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn hit_through_center() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, -1.0));
+        assert!(unit_box().hit(&ray, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn miss_beside() {
+        let ray = Ray::new(Point::new(2.0, 0.0, 5.0), Point::new(0.0, 0.0, -1.0));
+        assert!(!unit_box().hit(&ray, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn miss_behind() {
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, 1.0));
+        assert!(!unit_box().hit(&ray, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn surrounding() {
+        let a = Aabb::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Point::new(-1.0, 0.5, 0.0), Point::new(0.5, 2.0, 0.5));
+        let expected = Aabb::new(Point::new(-1.0, 0.0, 0.0), Point::new(1.0, 2.0, 1.0));
+        assert_eq!(Aabb::surrounding_box(&a, &b), expected);
+        assert_eq!(expected.longest_axis(), 1);
+    }
+
+    #[test]
+    fn flat_box_can_be_hit() {
+        let bbox = Aabb::from_points(&[
+            Point::new(-1.0, -1.0, 0.0),
+            Point::new(1.0, -1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ]);
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, -1.0));
+        assert!(bbox.hit(&ray, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn surface_area() {
+        assert_eq!(unit_box().surface_area(), 24.0);
+    }
+}
@@ -0,0 +1,161 @@
+use super::{Color, Point, Ray, World};
+
+use std::{fmt, str::FromStr};
+
+/// An arbitrary output variable, a picture of what the camera rays hit first
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Aov {
+    /// Distance from the camera
+    Depth,
+    /// World space normal, facing the camera
+    Normal,
+    /// Surface color of the material without lighting
+    Albedo,
+    /// World space position
+    Position,
+    /// Object id, the index of the object in the scene plus one
+    Id,
+}
+
+impl Aov {
+    /// The accepted names of the AOVs
+    pub const VARIANTS: [&'static str; 5] = ["depth", "normal", "albedo", "position", "id"];
+
+    /// Names of the channels of a float file, the first this many colors of a pixel are used
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Albedo => &["R", "G", "B"],
+            Aov::Id => &["ID"],
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "depth" => Ok(Aov::Depth),
+            "normal" => Ok(Aov::Normal),
+            "albedo" => Ok(Aov::Albedo),
+            "position" => Ok(Aov::Position),
+            "id" => Ok(Aov::Id),
+            _ => Err(format!("Unknown AOV: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Position => "position",
+            Aov::Id => "id",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where a camera ray hit the scene first, or the sum of these over the samples of a pixel
+///
+/// Everything is zero where the ray didn't hit anything
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FirstHit {
+    pub depth: f64,
+    pub normal: Point,
+    pub albedo: Color,
+    pub position: Point,
+    pub object_id: u32,
+}
+
+impl FirstHit {
+    pub fn find(ray: &Ray, world: &World) -> Self {
+        match world.objects.hit(ray, 0.001, f64::INFINITY) {
+            Some(hit) => Self {
+                depth: hit.t * ray.direction().len(),
+                normal: hit.normal,
+                albedo: hit.material.albedo(&hit),
+                position: hit.position,
+                object_id: hit.object_id,
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Add the next sample of the pixel
+    ///
+    /// The object id is kept from the first sample, the ids of the edges mustn't blend
+    pub fn accumulate(self, other: Self) -> Self {
+        Self {
+            depth: self.depth + other.depth,
+            normal: self.normal + other.normal,
+            albedo: self.albedo + other.albedo,
+            position: self.position + other.position,
+            object_id: self.object_id,
+        }
+    }
+
+    /// The average of the sums of `samples`, single values are in every channel
+    pub fn value(&self, aov: Aov, samples: usize) -> Color {
+        let scale = 1.0 / samples as f64;
+        let point = |p: Point| scale * Color::new(p.x(), p.y(), p.z());
+        match aov {
+            Aov::Depth => scale * Color::new(self.depth, self.depth, self.depth),
+            Aov::Normal => point(self.normal),
+            Aov::Albedo => scale * self.albedo,
+            Aov::Position => point(self.position),
+            Aov::Id => {
+                let id = self.object_id as f64;
+                Color::new(id, id, id)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Uniform;
+    use crate::material::Lambertian;
+    use crate::{HittableList, Sphere};
+    use std::sync::Arc;
+
+    #[test]
+    fn first_hit_of_a_sphere() {
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, -3.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6))),
+        )
+        .with_id(7);
+        let world = World {
+            objects: Box::new(HittableList::default().chain_add(Box::new(sphere))),
+            lights: HittableList::default(),
+            environment: Box::new(Uniform::new(Color::black())),
+        };
+
+        // Not a unit direction, the depth is still the distance
+        let ray = Ray::new(Point::default(), Point::new(0.0, 0.0, -4.0));
+        let hit = FirstHit::find(&ray, &world);
+        assert!((hit.depth - 2.0).abs() < 1e-9);
+        assert_eq!(hit.object_id, 7);
+
+        let miss = FirstHit::find(
+            &Ray::new(Point::default(), Point::new(0.0, 1.0, 0.0)),
+            &world,
+        );
+        assert_eq!(miss, FirstHit::default());
+
+        // Half of the samples missed, the id isn't averaged
+        let pixel = hit.accumulate(miss);
+        assert!((pixel.value(Aov::Depth, 2)[0] - 1.0).abs() < 1e-9);
+        assert_eq!(pixel.value(Aov::Normal, 2), Color::new(0.0, 0.0, 0.5));
+        assert_eq!(pixel.value(Aov::Albedo, 2), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(pixel.value(Aov::Id, 2), Color::new(7.0, 7.0, 7.0));
+        assert_eq!(miss.accumulate(hit).value(Aov::Id, 2), Color::black());
+    }
+}
@@ -0,0 +1,257 @@
+mod split;
+
+use super::{Aabb, HitRecord, Hittable, HittableList, Ray};
+
+use std::boxed::Box;
+
+pub use split::SplitMethod;
+
+/// An object together with its bounding box
+type Primitive = (Aabb, Box<dyn Hittable>);
+
+/// Bounding volume hierarchy
+///
+/// The objects are organized into a binary tree of bounding boxes,
+/// so a ray is only tested against the objects whose boxes it goes through.
+/// This makes the cost of a hit test logarithmic in the number of objects.
+#[derive(Debug)]
+pub struct Bvh {
+    root: Option<Node>,
+    /// Objects without a bounding box can not be put into the tree,
+    /// these are tested one by one
+    unbounded: HittableList,
+}
+
+/// Shape of the tree
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct BvhStats {
+    /// Number of interior nodes and leaves
+    pub nodes: usize,
+    /// Number of nodes on the longest path from the root to a leaf
+    pub depth: usize,
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf {
+        bbox: Aabb,
+        objects: Vec<Box<dyn Hittable>>,
+    },
+    Interior {
+        bbox: Aabb,
+        /// The axis along which the children were split
+        axis: usize,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Bvh {
+    pub fn new(list: HittableList) -> Self {
+        Self::build(list, SplitMethod::default())
+    }
+
+    pub fn build(list: HittableList, split_method: SplitMethod) -> Self {
+        let mut unbounded = HittableList::default();
+        let mut bounded = Vec::new();
+        for object in list.into_objects() {
+            match object.bounding_box() {
+                Some(bbox) => bounded.push((bbox, object)),
+                None => unbounded.add(object),
+            }
+        }
+
+        let root = if bounded.is_empty() {
+            None
+        } else {
+            Some(Node::build(bounded, split_method))
+        };
+
+        Self { root, unbounded }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.root
+            .as_ref()
+            .map_or_else(BvhStats::default, Node::stats)
+    }
+}
+
+impl From<HittableList> for Bvh {
+    fn from(list: HittableList) -> Self {
+        Self::new(list)
+    }
+}
+
+impl Node {
+    fn build(mut objects: Vec<Primitive>, split_method: SplitMethod) -> Self {
+        let bbox = objects
+            .iter()
+            .map(|(bbox, _)| *bbox)
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b))
+            .unwrap();
+
+        let centroid_bounds = objects
+            .iter()
+            .map(|(bbox, _)| Aabb::new(bbox.centroid(), bbox.centroid()))
+            .reduce(|a, b| Aabb::surrounding_box(&a, &b))
+            .unwrap();
+        let axis = centroid_bounds.longest_axis();
+
+        // A single object, or objects which can not be told apart
+        let split = if objects.len() == 1 || centroid_bounds.diagonal()[axis] <= 0.0 {
+            None
+        } else {
+            split::partition(split_method, &mut objects, &bbox, &centroid_bounds, axis)
+        };
+
+        match split {
+            Some(left_count) => {
+                let right = objects.split_off(left_count);
+                Node::Interior {
+                    bbox,
+                    axis,
+                    left: Box::new(Node::build(objects, split_method)),
+                    right: Box::new(Node::build(right, split_method)),
+                }
+            }
+            None => Node::Leaf {
+                bbox,
+                objects: objects.into_iter().map(|(_, object)| object).collect(),
+            },
+        }
+    }
+
+    fn stats(&self) -> BvhStats {
+        match self {
+            Node::Leaf { .. } => BvhStats { nodes: 1, depth: 1 },
+            Node::Interior { left, right, .. } => {
+                let (left, right) = (left.stats(), right.stats());
+                BvhStats {
+                    nodes: 1 + left.nodes + right.nodes,
+                    depth: 1 + left.depth.max(right.depth),
+                }
+            }
+        }
+    }
+
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bbox, .. } | Node::Interior { bbox, .. } => bbox,
+        }
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !self.bbox().hit(r, t_min, t_max) {
+            return None;
+        }
+
+        match self {
+            Node::Leaf { objects, .. } => objects.iter().fold(None, |best, obj| {
+                let closest_so_far = best.as_ref().map_or(t_max, |x| x.t);
+                obj.hit(r, t_min, closest_so_far).or(best)
+            }),
+            Node::Interior {
+                axis, left, right, ..
+            } => {
+                // Visit the closer child first,
+                // so the further one can be skipped more often
+                let (first, second) = if r.direction()[*axis] < 0.0 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+
+                let first_hit = first.hit(r, t_min, t_max);
+                let closest_so_far = first_hit.as_ref().map_or(t_max, |x| x.t);
+                second.hit(r, t_min, closest_so_far).or(first_hit)
+            }
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let tree_hit = self
+            .root
+            .as_ref()
+            .and_then(|root| root.hit(r, t_min, t_max));
+        let closest_so_far = tree_hit.as_ref().map_or(t_max, |x| x.t);
+        self.unbounded.hit(r, t_min, closest_so_far).or(tree_hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.root.as_ref().map(|root| *root.bbox())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color, Point, Sphere};
+    use std::sync::Arc;
+
+    const SPLIT_METHODS: [SplitMethod; 3] = [
+        SplitMethod::Sah,
+        SplitMethod::Middle,
+        SplitMethod::EqualCounts,
+    ];
+
+    fn spheres() -> HittableList {
+        let material = Arc::new(Lambertian::new(Color::white()));
+        (0..50).fold(HittableList::default(), |list, i| {
+            let i = i as f64;
+            let center = Point::new((i * 1.7).sin() * 10.0, (i * 0.3).cos() * 10.0, -i);
+            list.chain_add(Box::new(Sphere::new(center, 0.7, material.clone())))
+        })
+    }
+
+    #[test]
+    fn same_hits_as_list() {
+        let list = spheres();
+        for &method in SPLIT_METHODS.iter() {
+            let bvh = Bvh::build(spheres(), method);
+            for i in 0..200 {
+                let i = i as f64;
+                let direction = Point::new((i * 0.13).sin(), (i * 0.71).cos(), -1.0);
+                let ray = Ray::new(Point::new(0.0, 0.0, 10.0), direction);
+                let expected = list.hit(&ray, 0.001, f64::INFINITY).map(|h| h.t);
+                let actual = bvh.hit(&ray, 0.001, f64::INFINITY).map(|h| h.t);
+                assert_eq!(expected, actual, "split method: {}", method);
+            }
+        }
+    }
+
+    #[test]
+    fn bounding_box_of_all_objects() {
+        for &method in SPLIT_METHODS.iter() {
+            assert_eq!(
+                spheres().bounding_box(),
+                Bvh::build(spheres(), method).bounding_box()
+            );
+        }
+    }
+
+    #[test]
+    fn equal_counts_is_balanced() {
+        let stats = Bvh::build(spheres(), SplitMethod::EqualCounts).stats();
+        assert_eq!(
+            stats,
+            BvhStats {
+                nodes: 99,
+                depth: 7
+            }
+        );
+    }
+
+    #[test]
+    fn split_method_names() {
+        for &method in SPLIT_METHODS.iter() {
+            assert_eq!(method.to_string().parse(), Ok(method));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use super::Primitive;
+use crate::Aabb;
+
+use std::{fmt, str::FromStr};
+
+/// Number of buckets the surface area heuristic is evaluated at
+const BUCKETS: usize = 12;
+
+/// The surface area heuristic doesn't create a leaf with more objects than this
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Cost of visiting an interior node relative to testing an object
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// How the objects of a node are divided between its two children
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// Surface area heuristic with binning
+    ///
+    /// Picks the split which minimizes the expected cost of a hit test,
+    /// assuming that the chance of hitting a box is proportional to its surface area
+    #[default]
+    Sah,
+    /// Split at the middle of the centroids' extent
+    Middle,
+    /// Split so both children get the same number of objects
+    EqualCounts,
+}
+
+impl SplitMethod {
+    /// The accepted names of the split methods
+    pub const VARIANTS: [&'static str; 3] = ["sah", "middle", "equal"];
+}
+
+impl FromStr for SplitMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "sah" => Ok(SplitMethod::Sah),
+            "middle" => Ok(SplitMethod::Middle),
+            "equal" => Ok(SplitMethod::EqualCounts),
+            _ => Err(format!("Unknown BVH split method: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for SplitMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SplitMethod::Sah => "sah",
+            SplitMethod::Middle => "middle",
+            SplitMethod::EqualCounts => "equal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Reorder the objects so the ones going to the left child come first
+///
+/// Returns the number of objects in the left child,
+/// or `None` if the objects should rather stay together in a leaf
+pub(super) fn partition(
+    method: SplitMethod,
+    objects: &mut [Primitive],
+    bbox: &Aabb,
+    centroid_bounds: &Aabb,
+    axis: usize,
+) -> Option<usize> {
+    match method {
+        SplitMethod::Sah => sah(objects, bbox, centroid_bounds, axis),
+        SplitMethod::Middle => Some(middle(objects, centroid_bounds, axis)),
+        SplitMethod::EqualCounts => Some(equal_counts(objects, axis)),
+    }
+}
+
+fn middle(objects: &mut [Primitive], centroid_bounds: &Aabb, axis: usize) -> usize {
+    let mid = centroid_bounds.centroid()[axis];
+    let left_count = partition_by(objects, |(bbox, _)| bbox.centroid()[axis] < mid);
+
+    // Every centroid is on the same side
+    if left_count == 0 || left_count == objects.len() {
+        equal_counts(objects, axis)
+    } else {
+        left_count
+    }
+}
+
+fn equal_counts(objects: &mut [Primitive], axis: usize) -> usize {
+    let mid = objects.len() / 2;
+    objects.select_nth_unstable_by(mid, |(a, _), (b, _)| {
+        a.centroid()[axis].total_cmp(&b.centroid()[axis])
+    });
+    mid
+}
+
+fn sah(
+    objects: &mut [Primitive],
+    bbox: &Aabb,
+    centroid_bounds: &Aabb,
+    axis: usize,
+) -> Option<usize> {
+    // Not worth the effort for a few objects
+    if objects.len() <= 2 {
+        return Some(equal_counts(objects, axis));
+    }
+
+    let start = centroid_bounds.min()[axis];
+    let extent = centroid_bounds.diagonal()[axis];
+    let bucket_of = |bbox: &Aabb| {
+        let offset = (bbox.centroid()[axis] - start) / extent;
+        ((offset * BUCKETS as f64) as usize).min(BUCKETS - 1)
+    };
+
+    let mut counts = [0; BUCKETS];
+    let mut bounds: [Option<Aabb>; BUCKETS] = [None; BUCKETS];
+    for (object_bbox, _) in objects.iter() {
+        let bucket = bucket_of(object_bbox);
+        counts[bucket] += 1;
+        bounds[bucket] = Some(surround(bounds[bucket], object_bbox));
+    }
+
+    // Sum up the buckets on each side of a split
+    let side = |buckets: std::ops::Range<usize>| {
+        buckets.fold((0, None), |(count, bbox), i| match &bounds[i] {
+            Some(b) => (count + counts[i], Some(surround(bbox, b))),
+            None => (count, bbox),
+        })
+    };
+
+    // The expected cost of splitting after each bucket
+    let (best_bucket, best_cost) = (0..BUCKETS - 1)
+        .map(|i| match (side(0..i + 1), side(i + 1..BUCKETS)) {
+            ((left_count, Some(left)), (right_count, Some(right))) => {
+                let left_cost = left_count as f64 * left.surface_area();
+                let right_cost = right_count as f64 * right.surface_area();
+                (
+                    i,
+                    TRAVERSAL_COST + (left_cost + right_cost) / bbox.surface_area(),
+                )
+            }
+            _ => (i, f64::INFINITY),
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+
+    let leaf_cost = objects.len() as f64;
+    if !best_cost.is_finite() {
+        // Every centroid fell into the same bucket
+        Some(equal_counts(objects, axis))
+    } else if objects.len() > MAX_LEAF_SIZE || best_cost < leaf_cost {
+        Some(partition_by(objects, |(bbox, _)| {
+            bucket_of(bbox) <= best_bucket
+        }))
+    } else {
+        None
+    }
+}
+
+fn surround(a: Option<Aabb>, b: &Aabb) -> Aabb {
+    a.map_or(*b, |a| Aabb::surrounding_box(&a, b))
+}
+
+/// Move the items matching the predicate to the front
+///
+/// Returns the number of matching items
+fn partition_by<T>(items: &mut [T], pred: impl Fn(&T) -> bool) -> usize {
+    let mut first = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
@@ -1,43 +1,294 @@
-use super::{Point, Ray};
+use super::{Hittable, Point, Ray, Sampler};
+use std::f64::consts::PI;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Camera {
     origin: Point,
+    /// Orthonormal basis, the camera looks towards -w
+    u: Point,
+    v: Point,
+    w: Point,
+    viewport_width: f64,
+    viewport_height: f64,
+    lens: Lens,
+    /// The rays are sent at random times between these, in frames
+    shutter: (f64, f64),
     lower_left_corner: Point,
     horizontal: Point,
     vertical: Point,
 }
 
+/// The aperture of a thin lens
+///
+/// Points at the focus distance are sharp,
+/// the rest are blurred in the shape of the aperture
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lens {
+    /// Diameter of the aperture, zero for a pinhole camera
+    pub aperture: f64,
+    /// Distance of the plane in focus from the camera
+    pub focus_distance: f64,
+    /// Number of straight blades for a polygonal aperture, `None` for a round one
+    pub blades: Option<u32>,
+    /// Rotation of the blades in degrees
+    pub rotation: f64,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Self {
+            aperture: 0.0,
+            focus_distance: 1.0,
+            blades: None,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Lens {
+    /// A random point of the aperture with unit radius
+    fn sample(&self, sampler: &mut Sampler) -> (f64, f64) {
+        match self.blades {
+            None => loop {
+                let (x, y) = (sampler.range(-1.0, 1.0), sampler.range(-1.0, 1.0));
+                if x * x + y * y < 1.0 {
+                    return (x, y);
+                }
+            },
+            Some(blades) => {
+                // The polygon is made of equal triangles around the center,
+                // choose one of them and a uniform point in it
+                let sector = 2.0 * PI / blades as f64;
+                let sector_index = sampler.index(blades as usize);
+                let start = self.rotation.to_radians() + sector * sector_index as f64;
+                let end = start + sector;
+
+                let s = sampler.next_f64().sqrt();
+                let b1 = sampler.next_f64() * s;
+                let b2 = s - b1;
+                (
+                    b1 * start.cos() + b2 * end.cos(),
+                    b1 * start.sin() + b2 * end.sin(),
+                )
+            }
+        }
+    }
+}
+
 impl Camera {
-    pub fn new(origin: Point, aspect_ratio: f64) -> Self {
-        let viewport_height = 2.0;
+    /// Create a pinhole camera at `look_from` which looks at `look_at`
+    ///
+    /// `vup` is the direction which is up on the image,
+    /// it is projected onto the image plane so it doesn't have to be perpendicular to the view.
+    /// `vfov` is the vertical field of view in degrees.
+    pub fn new(look_from: Point, look_at: Point, vup: Point, vfov: f64, aspect_ratio: f64) -> Self {
+        let viewport_height = 2.0 * (vfov.to_radians() / 2.0).tan();
         let viewport_width = aspect_ratio * viewport_height;
-        let focal_length = 1.0;
-
-        let horizontal = Point::new(viewport_width, 0.0, 0.0);
-        let vertical = Point::new(0.0, viewport_height, 0.0);
-        let lower_left_corner = {
-            let half_horizontal = horizontal / 2.0;
-            let half_vertical = vertical / 2.0;
-            let focal = Point::new(0.0, 0.0, focal_length);
-            origin - half_horizontal - half_vertical - focal
-        };
+
+        let w = (look_from - look_at).unit_vector();
+        let u = Point::cross(&vup, &w).unit_vector();
+        let v = Point::cross(&w, &u);
 
         Self {
-            origin,
-            horizontal,
-            vertical,
-            lower_left_corner,
+            origin: look_from,
+            u,
+            v,
+            w,
+            viewport_width,
+            viewport_height,
+            lens: Lens::default(),
+            shutter: (0.0, 0.0),
+            lower_left_corner: Point::default(), // by with_lens
+            horizontal: Point::default(),        // by with_lens
+            vertical: Point::default(),          // by with_lens
+        }
+        .with_lens(Lens::default())
+    }
+
+    /// Use a thin lens for depth of field
+    pub fn with_lens(mut self, lens: Lens) -> Self {
+        // The image plane is moved to the focus distance,
+        // so the rays from every point of the lens meet there
+        let focus_distance = lens.focus_distance;
+        self.lens = lens;
+        self.horizontal = focus_distance * self.viewport_width * self.u;
+        self.vertical = focus_distance * self.viewport_height * self.v;
+        self.lower_left_corner =
+            self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - focus_distance * self.w;
+        self
+    }
+
+    /// Keep the shutter open between these times for motion blur
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    /// Focus on the first object in the center of the image
+    ///
+    /// The focus distance is kept if nothing is there
+    pub fn autofocus(self, world: &dyn Hittable) -> Self {
+        let center = Ray::new(self.origin, -self.w).with_time(self.shutter.0);
+        match world.hit(&center, 0.001, f64::INFINITY) {
+            Some(hit) => self.with_lens(Lens {
+                focus_distance: hit.t,
+                ..self.lens
+            }),
+            None => self,
         }
     }
 
+    pub fn focus_distance(&self) -> f64 {
+        self.lens.focus_distance
+    }
+
     pub fn get_direction(&self, u: f64, v: f64) -> Point {
         self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin
     }
 
     /// Create a ray which points from the camera to he real (x, y)
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+    ///
+    /// The ray starts from a random point of the lens,
+    /// at a random time while the shutter is open
+    pub fn get_ray(&self, u: f64, v: f64, sampler: &mut Sampler) -> Ray {
+        let offset = if self.lens.aperture > 0.0 {
+            let (x, y) = self.lens.sample(sampler);
+            let radius = self.lens.aperture / 2.0;
+            radius * (x * self.u + y * self.v)
+        } else {
+            Point::default()
+        };
         let direction = self.get_direction(u, v);
-        Ray::new(self.origin, direction)
+        let (open, close) = self.shutter;
+        let time = sampler.range(open, close);
+        Ray::new(self.origin + offset, direction - offset).with_time(time)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_at_the_target() {
+        let look_from = Point::new(1.0, 2.0, 3.0);
+        let look_at = Point::new(-2.0, 0.0, 1.0);
+        let camera = Camera::new(look_from, look_at, Point::new(0.0, 1.0, 0.0), 60.0, 1.5);
+
+        let center = camera.get_ray(0.5, 0.5, &mut Sampler::new(0, 0, 0));
+        assert_eq!(*center.origin(), look_from);
+        let expected = (look_at - look_from).unit_vector();
+        assert!((center.direction().unit_vector() - expected).near_zero());
+    }
+
+    #[test]
+    fn vertical_field_of_view() {
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            2.0,
+        );
+        // Half of the field of view above the center, the image is not rotated
+        let top = camera.get_direction(0.5, 1.0).unit_vector();
+        let expected = Point::new(0.0, 1.0, -1.0).unit_vector();
+        assert!((top - expected).near_zero());
+
+        // The aspect ratio widens the image
+        let right = camera.get_direction(1.0, 0.5);
+        assert!((right - Point::new(2.0, 0.0, -1.0)).near_zero());
+    }
+
+    #[test]
+    fn focus_plane_is_sharp() {
+        let lens = Lens {
+            aperture: 0.5,
+            focus_distance: 3.0,
+            blades: Some(6),
+            rotation: 15.0,
+        };
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+        )
+        .with_lens(lens);
+
+        // Every ray through the same pixel meets at the focus distance
+        let mut sampler = Sampler::new(0, 0, 0);
+        let target = camera.get_ray(0.3, 0.7, &mut sampler);
+        let target = target.point_at(1.0).unwrap();
+        for _ in 0..100 {
+            let ray = camera.get_ray(0.3, 0.7, &mut sampler);
+            assert!((ray.point_at(1.0).unwrap() - target).near_zero());
+            assert!(ray.origin().len() <= 0.25 + 1e-12);
+            assert_eq!(ray.origin().z(), 0.0);
+        }
+    }
+
+    #[test]
+    fn polygonal_aperture() {
+        let lens = Lens {
+            aperture: 2.0,
+            focus_distance: 1.0,
+            blades: Some(4),
+            rotation: 0.0,
+        };
+        let mut sampler = Sampler::new(0, 0, 0);
+        // A square with corners at (±1, 0) and (0, ±1)
+        for _ in 0..1000 {
+            let (x, y) = lens.sample(&mut sampler);
+            assert!(x.abs() + y.abs() <= 1.0 + 1e-12);
+        }
+
+        // Rotated to have horizontal and vertical edges
+        let lens = Lens {
+            rotation: 45.0,
+            ..lens
+        };
+        for _ in 0..1000 {
+            let (x, y) = lens.sample(&mut sampler);
+            assert!(x.abs().max(y.abs()) <= 0.5f64.sqrt() + 1e-12);
+        }
+    }
+
+    #[test]
+    fn autofocus_on_the_center() {
+        use crate::{material::Lambertian, Color, Sphere};
+        use std::sync::Arc;
+
+        let sphere = Sphere::new(
+            Point::new(0.0, 0.0, -5.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::white())),
+        );
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+        )
+        .autofocus(&sphere);
+        assert!((camera.focus_distance() - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rays_are_sent_while_the_shutter_is_open() {
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+        )
+        .with_shutter(0.25, 0.75);
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..100 {
+            let time = camera.get_ray(0.5, 0.5, &mut sampler).time();
+            assert!((0.25..=0.75).contains(&time));
+        }
     }
 }
@@ -0,0 +1,309 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::{aov::FirstHit, render::Pixel, Color, Config, Point};
+
+const MAGIC: &[u8; 8] = b"RTCHECK2";
+
+/// The state of an unfinished render, from which it can be continued
+///
+/// The pixels are kept as the sums of their samples with full precision,
+/// so continuing adds to them exactly like the render would have
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Of the settings which change the picture, see `config_hash`
+    pub config_hash: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Row by row from the top, with the samples taken in each
+    pub pixels: Vec<Pixel>,
+}
+
+impl Checkpoint {
+    /// Write the checkpoint next to the file first, so a render killed while writing
+    /// still has the previous checkpoint
+    ///
+    /// `render.ckpt` is written as `render.ckpt.tmp`, which can't be another file of the render
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let temporary = path.with_file_name(file_name);
+        let mut out = BufWriter::new(File::create(&temporary)?);
+        self.write(&mut out)?;
+        out.into_inner()?.sync_all()?;
+        fs::rename(temporary, path)
+    }
+
+    /// Only a checkpoint of a `width` x `height` picture is read
+    pub fn load(path: &Path, width: usize, height: usize) -> io::Result<Self> {
+        Self::read(&mut BufReader::new(File::open(path)?), width, height)
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        for value in &[self.config_hash, self.width as u64, self.height as u64] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for pixel in &self.pixels {
+            out.write_all(&(pixel.samples as u64).to_le_bytes())?;
+            let hit = &pixel.first_hit;
+            let values = [
+                pixel.color[0],
+                pixel.color[1],
+                pixel.color[2],
+                pixel.luminance_squares,
+                hit.depth,
+                hit.normal.x(),
+                hit.normal.y(),
+                hit.normal.z(),
+                hit.albedo[0],
+                hit.albedo[1],
+                hit.albedo[2],
+                hit.position.x(),
+                hit.position.y(),
+                hit.position.z(),
+            ];
+            for value in &values {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            out.write_all(&hit.object_id.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    pub fn read(input: &mut impl Read, width: usize, height: usize) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a checkpoint of this renderer",
+            ));
+        }
+        let config_hash = read_u64(input)?;
+        // Checked before anything is allocated for the pixels of a corrupt file
+        let size = (read_u64(input)?, read_u64(input)?);
+        let expected = (width as u64, height as u64);
+        if size != expected || width.checked_mul(height).is_none() {
+            let message = format!(
+                "The checkpoint is of a {}x{} picture, not {}x{}",
+                size.0, size.1, width, height
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for _ in 0..width * height {
+            let samples = read_u64(input)? as usize;
+            let mut values = [0.0; 14];
+            for value in values.iter_mut() {
+                *value = f64::from_bits(read_u64(input)?);
+            }
+            let mut id = [0; 4];
+            input.read_exact(&mut id)?;
+            let v = values;
+            pixels.push(Pixel {
+                color: Color::new(v[0], v[1], v[2]),
+                luminance_squares: v[3],
+                first_hit: FirstHit {
+                    depth: v[4],
+                    normal: Point::new(v[5], v[6], v[7]),
+                    albedo: Color::new(v[8], v[9], v[10]),
+                    position: Point::new(v[11], v[12], v[13]),
+                    object_id: u32::from_le_bytes(id),
+                },
+                samples,
+            });
+        }
+        Ok(Self {
+            config_hash,
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Identifies the settings a checkpoint can only be continued with
+///
+/// The size, the ray depth, the adaptive sampling, the seed, the sky, the scene and
+/// whether the first hits are found count.
+/// The max samples of the adaptive sampling count too, they decide where its rounds end.
+/// Otherwise the samples, the output and the render settings of the scene file may change.
+/// The files the scene loads, like meshes and textures, aren't checked
+pub fn config_hash(config: &Config) -> u64 {
+    let mut scene = config.scene.clone();
+    scene.render = Default::default();
+    let first_hits = !config.aovs.is_empty() || config.denoise;
+    let adaptive = config.adaptive.map(|adaptive| {
+        (
+            adaptive.threshold,
+            adaptive.min_samples,
+            config.samples_per_pixel,
+        )
+    });
+    let description = format!(
+        "{}x{} {} {:?} {} {} {} {:?}",
+        config.img_width,
+        config.img_height,
+        config.max_ray_depth,
+        adaptive,
+        config.seed,
+        config.sky,
+        first_hits,
+        scene
+    );
+    fnv1a(description.as_bytes())
+}
+
+/// 64-bit FNV-1a, unlike the std hasher it is the same in every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let pixel = |i: usize| Pixel {
+            color: Color::new(i as f64 / 3.0, 1e-300, 7.0),
+            first_hit: FirstHit {
+                depth: 2.5,
+                normal: Point::new(0.0, -1.0, 0.1),
+                albedo: Color::new(0.1, 0.2, 0.3),
+                position: Point::new(-4.0, 5.0, 6.0),
+                object_id: i as u32,
+            },
+            luminance_squares: 0.125,
+            samples: i,
+        };
+        let checkpoint = Checkpoint {
+            config_hash: 0xdead_beef,
+            width: 3,
+            height: 2,
+            pixels: (0..6).map(pixel).collect(),
+        };
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes).unwrap();
+        let read = |bytes: &[u8]| Checkpoint::read(&mut &bytes[..], 3, 2);
+        assert_eq!(read(&bytes).unwrap(), checkpoint);
+
+        // Cut short
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read(b"P6\n3 2\n255\n").is_err());
+        // Another size
+        let error = Checkpoint::read(&mut bytes.as_slice(), 2, 3).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // A size which would overflow is rejected before allocating
+        let mut huge = bytes[..16].to_vec();
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(read(&huge).is_err());
+    }
+
+    #[test]
+    fn saved_through_a_temporary_file() {
+        let directory = std::env::temp_dir();
+        let path = directory.join(format!("checkpoint-{}.ckpt", std::process::id()));
+        // Only the extension differs, it mustn't be overwritten
+        let other = path.with_extension("tmp");
+        fs::write(&other, b"other").unwrap();
+
+        let checkpoint = Checkpoint {
+            config_hash: 1,
+            width: 1,
+            height: 1,
+            pixels: vec![Pixel::default()],
+        };
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path, 1, 1);
+        let other_contents = fs::read(&other).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&other).unwrap();
+
+        assert_eq!(loaded.unwrap(), checkpoint);
+        assert_eq!(other_contents, b"other");
+    }
+
+    #[test]
+    fn resumed_render_is_the_same() {
+        use crate::{
+            environment::Gradient, material::Lambertian, render::Renderer, Camera, HittableList,
+            Sphere, World,
+        };
+        use std::sync::Arc;
+
+        let mut objects = HittableList::default();
+        objects.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3))),
+        )));
+        let world = World {
+            objects: Box::new(objects),
+            lights: HittableList::default(),
+            environment: Box::new(Gradient::new(Color::white(), Color::new(0.5, 0.7, 1.0))),
+        };
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            1.0,
+        );
+        let renderer = Renderer {
+            camera: &camera,
+            world: &world,
+            width: 4,
+            height: 4,
+            max_ray_depth: 5,
+            find_first_hits: true,
+            adaptive: None,
+            seed: 1,
+        };
+
+        let mut straight = vec![Pixel::default(); 16];
+        renderer.render(&mut straight, 8, |_| {});
+
+        let mut interrupted = vec![Pixel::default(); 16];
+        renderer.render(&mut interrupted, 3, |_| {});
+        let mut bytes = Vec::new();
+        let checkpoint = Checkpoint {
+            config_hash: 0,
+            width: 4,
+            height: 4,
+            pixels: interrupted,
+        };
+        checkpoint.write(&mut bytes).unwrap();
+        let mut resumed = Checkpoint::read(&mut bytes.as_slice(), 4, 4)
+            .unwrap()
+            .pixels;
+        renderer.render(&mut resumed, 8, |_| {});
+
+        // Bit for bit
+        assert_eq!(resumed, straight);
+    }
+
+    #[test]
+    fn known_hash() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
+use serde::Deserialize;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
 pub struct Color(f64, f64, f64);
 
 impl Color {
@@ -6,18 +8,22 @@ impl Color {
         Self(r, g, b)
     }
 
-    /// Get colors encoded as RGB bytes
+    /// Get colors encoded as sRGB bytes, clamped to white
     pub fn rgb_bytes(&self, samples_per_pixel: usize) -> (u8, u8, u8) {
-        use std::u8::{MAX, MIN};
-        let f64_to_u8 = |x: f64| x.min(MAX as f64).max(MIN as f64) as u8;
+        let f64_to_u8 = |x: f64| (255.0 * srgb_encode(x)).round() as u8;
 
         let scale = 1.0 / (samples_per_pixel as f64);
 
-        return (
-            f64_to_u8(256.0 * (self.0 * scale).sqrt()),
-            f64_to_u8(256.0 * (self.1 * scale).sqrt()),
-            f64_to_u8(256.0 * (self.2 * scale).sqrt()),
-        );
+        (
+            f64_to_u8(self.0 * scale),
+            f64_to_u8(self.1 * scale),
+            f64_to_u8(self.2 * scale),
+        )
+    }
+
+    /// Relative luminance of the linear sRGB color
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
     }
 
     /// Get colors encoded as space separated RGB stringified bytes
@@ -47,6 +53,27 @@ impl Color {
     }
 }
 
+/// The sRGB transfer function, from linear light to the encoded value
+///
+/// The value is clamped between 0 and 1
+pub fn srgb_encode(linear: f64) -> f64 {
+    let linear = linear.clamp(0.0, 1.0);
+    if linear <= 0.003_130_8 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of `srgb_encode`
+pub fn srgb_decode(encoded: f64) -> f64 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 impl_immutable_op!(Color, Add, add);
 impl_immutable_op!(Color, Sub, sub);
@@ -73,4 +100,15 @@ mod tests {
     fn test_white() {
         assert_eq!((255, 255, 255), Color::white().rgb_bytes(1));
     }
+
+    #[test]
+    fn srgb_round_trip() {
+        for i in 0..=255 {
+            let encoded = i as f64 / 255.0;
+            assert!((srgb_encode(srgb_decode(encoded)) - encoded).abs() < 1e-12);
+        }
+        // Middle gray is encoded brighter, overexposed colors are clamped
+        assert_eq!(Color::new(0.18, 0.18, 0.18).rgb_bytes(1), (118, 118, 118));
+        assert_eq!(Color::new(4.0, 0.5, -1.0).rgb_bytes(2), (255, 137, 0));
+    }
 }
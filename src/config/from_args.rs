@@ -1,5 +1,8 @@
-use super::Config;
-use clap::{App, Arg};
+use super::{Adaptive, Aov, Config, Progressive, SceneDescription, SplitMethod};
+use crate::output::{Format, OutputError, ToneMapping, ToneOperator};
+use crate::Point;
+use clap::{App, Arg, ErrorKind};
+use std::path::Path;
 
 fn positive_int(s: String) -> Result<(), String> {
     s.parse::<usize>()
@@ -13,6 +16,42 @@ fn positive_int(s: String) -> Result<(), String> {
         })
 }
 
+fn non_negative_int(s: String) -> Result<(), String> {
+    s.parse::<u64>().map(|_| ()).map_err(|err| err.to_string())
+}
+
+fn point(s: String) -> Result<(), String> {
+    s.parse::<Point>().map(|_| ())
+}
+
+fn non_negative_float(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(v) if v >= 0.0 => Ok(()),
+        Ok(_) => Err(String::from("Expected a non negative value")),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn float(s: String) -> Result<(), String> {
+    s.parse::<f64>().map(|_| ()).map_err(|err| err.to_string())
+}
+
+fn positive_float(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(v) if v > 0.0 => Ok(()),
+        Ok(_) => Err(String::from("Expected a positive value")),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn angle(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(v) if 0.0 < v && v < 180.0 => Ok(()),
+        Ok(_) => Err(String::from("Expected an angle between 0 and 180 degrees")),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
 fn positive_int_or_alias(s: String) -> Result<(), String> {
     match s.to_uppercase().as_ref() {
         "HD" => Ok(()),
@@ -51,9 +90,41 @@ impl Config {
                     .short("s")
                     .long("samples")
                     .default_value("100")
+                    .help("Samples per pixel, with adaptive sampling the default of --max-samples")
+                    .validator(positive_int)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("adaptive")
+                    .long("adaptive")
+                    .value_name("error")
+                    .validator(positive_float)
+                    .help("Stop sampling the pixels once their standard error is below this part of their brightness, like 0.02")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("min samples")
+                    .long("min-samples")
+                    .value_name("samples")
+                    .requires("adaptive")
                     .validator(positive_int)
+                    .help("Samples per pixel before the adaptive sampling can stop [default: 16, at most the max samples]")
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("max samples")
+                    .long("max-samples")
+                    .value_name("samples")
+                    .requires("adaptive")
+                    .validator(positive_int)
+                    .help("The most samples a pixel gets with adaptive sampling [default: the samples per pixel]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("heatmap")
+                    .long("heatmap")
+                    .help("Also write the samples spent per pixel, into a file like img.samples.png"),
+            )
             .arg(
                 Arg::with_name("max ray depth")
                     .short("d")
@@ -62,17 +133,263 @@ impl Config {
                     .validator(positive_int)
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("seed")
+                    .long("seed")
+                    .value_name("n")
+                    .default_value("0")
+                    .validator(non_negative_int)
+                    .help("Decides the random numbers, the same seed and settings render the same picture")
+                    .takes_value(true),
+            )
             .arg(
                 Arg::with_name("output path")
                     .short("o")
                     .long("output")
                     .default_value("img.png")
-                    .help("Only png is supported")
+                    .help("The extension picks the format, exr, hdr and pfm keep the linear colors")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("exposure")
+                    .long("exposure")
+                    .value_name("stops")
+                    .allow_hyphen_values(true)
+                    .validator(float)
+                    .help("Brighten or darken the 8-bit outputs, every stop doubles the light [default: 0]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("tone operator")
+                    .long("tone-map")
+                    .value_name("operator")
+                    .possible_values(&ToneOperator::VARIANTS)
+                    .help("How the highlights are fit into the 8-bit outputs [default: clamp]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("white point")
+                    .long("white-point")
+                    .value_name("brightness")
+                    .validator(positive_float)
+                    .help("The brightness which becomes white for extended-reinhard and hable [default: 4 and 11.2]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("aov")
+                    .long("aov")
+                    .value_name("name")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .use_delimiter(true)
+                    .possible_values(&Aov::VARIANTS)
+                    .help("Also write what the camera rays hit first, into files like img.depth.exr, or .pfm next to a .pfm picture")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("aov layers")
+                    .long("aov-layers")
+                    .requires("aov")
+                    .help("Write the AOVs as layers of the picture, it has to be an .exr"),
+            )
+            .arg(
+                Arg::with_name("denoise")
+                    .long("denoise")
+                    .help("Filter the noise out of the picture, the edges are found by the albedo, normals and depth"),
+            )
+            .arg(
+                Arg::with_name("save raw")
+                    .long("save-raw")
+                    .requires("denoise")
+                    .help("Also write the picture before denoising, into a file like img.raw.png"),
+            )
+            .arg(
+                Arg::with_name("progressive")
+                    .long("progressive")
+                    .help("Render one sample per pixel at a time, writing the picture now and then. Ctrl+C stops and saves it"),
+            )
+            .arg(
+                Arg::with_name("snapshot seconds")
+                    .long("snapshot-every")
+                    .value_name("seconds")
+                    .requires("progressive")
+                    .validator(positive_float)
+                    .help("Time between the snapshots of a progressive render [default: 10 if there are no snapshot passes]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("snapshot passes")
+                    .long("snapshot-passes")
+                    .value_name("passes")
+                    .requires("progressive")
+                    .validator(positive_int)
+                    .help("Samples per pixel between the snapshots of a progressive render")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("time limit")
+                    .long("time-limit")
+                    .value_name("seconds")
+                    .requires("progressive")
+                    .validator(positive_float)
+                    .help("Stop a progressive render after this time even if the samples aren't done")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("checkpoint")
+                    .long("checkpoint")
+                    .value_name("path")
+                    .help("Save the state of the render now and then, and at the end, to continue it with --resume [default: the resumed checkpoint]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("checkpoint seconds")
+                    .long("checkpoint-every")
+                    .value_name("seconds")
+                    .validator(positive_float)
+                    .default_value("60")
+                    .help("Time between the checkpoints, progressive renders save them with the snapshots")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("resume")
+                    .long("resume")
+                    .value_name("checkpoint")
+                    .help("Continue an interrupted render, or add samples to a finished one. The settings and the scene must be the same")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("no sky")
+                    .long("no-sky")
+                    .help("Black background, the scene is lit only by its lights"),
+            )
+            .arg(
+                Arg::with_name("bvh split method")
+                    .long("bvh")
+                    .default_value("sah")
+                    .possible_values(&SplitMethod::VARIANTS)
+                    .help("How the bounding volume hierarchy divides the objects")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("look from")
+                    .long("look-from")
+                    .value_name("x,y,z")
+                    .allow_hyphen_values(true)
+                    .validator(point)
+                    .help("Position of the camera [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("look at")
+                    .long("look-at")
+                    .value_name("x,y,z")
+                    .allow_hyphen_values(true)
+                    .validator(point)
+                    .help("The point in the center of the image [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("view up")
+                    .long("vup")
+                    .value_name("x,y,z")
+                    .allow_hyphen_values(true)
+                    .validator(point)
+                    .help("The direction which is up on the image [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("vertical fov")
+                    .long("vfov")
+                    .value_name("degrees")
+                    .validator(angle)
+                    .help("Vertical field of view [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("aperture")
+                    .long("aperture")
+                    .value_name("diameter")
+                    .validator(non_negative_float)
+                    .help("Lens size for depth of field, 0 is sharp everywhere [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("focus distance")
+                    .long("focus-distance")
+                    .value_name("distance")
+                    .validator(non_negative_float)
+                    .help("Distance of the sharp plane [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("autofocus")
+                    .long("autofocus")
+                    .conflicts_with("focus distance")
+                    .help("Focus on the first object in the center of the image"),
+            )
+            .arg(
+                Arg::with_name("shutter")
+                    .long("shutter")
+                    .value_names(&["open", "close"])
+                    .validator(non_negative_float)
+                    .help("When the shutter opens and closes, in frames [default: from the scene]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("scene file")
+                    .long("scene")
+                    .value_name("path")
+                    .help("TOML scene description [default: built-in scene]")
                     .takes_value(true),
             )
             .get_matches();
 
-        let height = matches
+        let mut scene = match matches.value_of("scene file") {
+            Some(path) => SceneDescription::load(Path::new(path)).unwrap_or_else(|err| {
+                clap::Error::with_description(&err.to_string(), ErrorKind::InvalidValue).exit()
+            }),
+            None => SceneDescription::default_scene(),
+        };
+
+        let camera = &mut scene.camera;
+        let point_arg = |name| matches.value_of(name).map(|p| p.parse().unwrap());
+        if let Some(look_from) = point_arg("look from") {
+            camera.look_from = look_from;
+        }
+        if let Some(look_at) = point_arg("look at") {
+            camera.look_at = look_at;
+        }
+        if let Some(vup) = point_arg("view up") {
+            camera.vup = vup;
+        }
+        if let Some(vfov) = matches.value_of("vertical fov") {
+            camera.vfov = vfov.parse().unwrap();
+        }
+        if let Some(aperture) = matches.value_of("aperture") {
+            camera.aperture = aperture.parse().unwrap();
+        }
+        if let Some(focus_distance) = matches.value_of("focus distance") {
+            camera.focus_distance = Some(focus_distance.parse().unwrap());
+            camera.autofocus = false;
+        }
+        if matches.is_present("autofocus") {
+            camera.autofocus = true;
+        }
+        if let Some(mut shutter) = matches.values_of("shutter") {
+            camera.shutter_open = shutter.next().unwrap().parse().unwrap();
+            camera.shutter_close = shutter.next().unwrap().parse().unwrap();
+        }
+        if let Err((_, message)) = camera.validate() {
+            clap::Error::with_description(&message, ErrorKind::InvalidValue).exit()
+        }
+
+        let settings = &scene.render;
+
+        // Values given on the command line take precedence over the scene file
+        let given = |name| matches.occurrences_of(name) > 0;
+
+        let height_arg = matches
             .value_of("image height")
             .map(|v| match v.to_uppercase().as_ref() {
                 "HD" => "720",
@@ -83,31 +400,159 @@ impl Config {
             .and_then(|w| w.parse().ok())
             .unwrap();
 
+        let height = match settings.height {
+            Some(height) if !given("image height") => height,
+            _ => height_arg,
+        };
+
         let height_from_width = (height as f64 * 16.0 / 9.0) as usize;
 
         let width = match matches.value_of("image width") {
             Some(val) => val.parse().unwrap(),
-            None => height_from_width,
+            None => settings.width.unwrap_or(height_from_width),
+        };
+
+        let mut samples_per_pixel = match settings.samples {
+            Some(samples) if !given("samples per pixel") => samples,
+            _ => matches
+                .value_of("samples per pixel")
+                .and_then(|s| s.parse().ok())
+                .unwrap(),
+        };
+
+        let adaptive = matches.value_of("adaptive").map(|threshold| {
+            // The noisy pixels get the max samples instead
+            if let Some(max_samples) = matches.value_of("max samples") {
+                samples_per_pixel = max_samples.parse().unwrap();
+            }
+            let min_samples = matches
+                .value_of("min samples")
+                .map_or(16.min(samples_per_pixel), |m| m.parse().unwrap());
+            if min_samples > samples_per_pixel {
+                let message = format!(
+                    "The min samples ({}) are more than the max samples ({})",
+                    min_samples, samples_per_pixel
+                );
+                clap::Error::with_description(&message, ErrorKind::InvalidValue).exit()
+            }
+            Adaptive {
+                threshold: threshold.parse().unwrap(),
+                min_samples,
+            }
+        });
+        let heatmap = matches.is_present("heatmap");
+
+        let max_ray_depth = match settings.ray_depth {
+            Some(depth) if !given("max ray depth") => depth,
+            _ => matches
+                .value_of("max ray depth")
+                .and_then(|d| d.parse().ok())
+                .unwrap(),
         };
 
-        let samples_per_pixel = matches
-            .value_of("samples per pixel")
+        let seed = matches
+            .value_of("seed")
             .and_then(|s| s.parse().ok())
             .unwrap();
 
-        let max_ray_depth = matches
-            .value_of("max ray depth")
-            .and_then(|d| d.parse().ok())
+        let output_file = match &settings.output {
+            Some(output) if !given("output path") => output.clone(),
+            _ => matches.value_of("output path").unwrap().to_owned(),
+        };
+
+        if Format::from_path(Path::new(&output_file)).is_none() {
+            let message = format!("{}: {}", output_file, OutputError::UnknownFormat);
+            clap::Error::with_description(&message, ErrorKind::InvalidValue).exit()
+        }
+
+        let tone_mapping = ToneMapping {
+            exposure: match matches.value_of("exposure") {
+                Some(exposure) => exposure.parse().unwrap(),
+                None => settings.exposure.unwrap_or(0.0),
+            },
+            operator: match matches.value_of("tone operator") {
+                Some(operator) => operator.parse().unwrap(),
+                None => settings.tone_map.unwrap_or_default(),
+            },
+            white_point: match matches.value_of("white point") {
+                Some(white_point) => Some(white_point.parse().unwrap()),
+                None => settings.white_point,
+            },
+        };
+
+        // A repeated AOV is written once
+        let mut aovs = Vec::new();
+        for aov in matches.values_of("aov").into_iter().flatten() {
+            let aov: Aov = aov.parse().unwrap();
+            if !aovs.contains(&aov) {
+                aovs.push(aov);
+            }
+        }
+        let aov_layers = matches.is_present("aov layers");
+        if aov_layers && Format::from_path(Path::new(&output_file)) != Some(Format::Exr) {
+            let message = format!("{}: the AOV layers need an .exr output", output_file);
+            clap::Error::with_description(&message, ErrorKind::InvalidValue).exit()
+        }
+
+        let denoise = matches.is_present("denoise");
+        let save_raw = matches.is_present("save raw");
+
+        let progressive = if matches.is_present("progressive") {
+            let snapshot_passes = matches
+                .value_of("snapshot passes")
+                .map(|p| p.parse().unwrap());
+            let snapshot_seconds = match matches.value_of("snapshot seconds") {
+                Some(seconds) => Some(seconds.parse().unwrap()),
+                None if snapshot_passes.is_none() => Some(10.0),
+                None => None,
+            };
+            Some(Progressive {
+                snapshot_seconds,
+                snapshot_passes,
+                time_limit: matches.value_of("time limit").map(|t| t.parse().unwrap()),
+            })
+        } else {
+            None
+        };
+
+        let resume = matches.value_of("resume").map(String::from);
+        let checkpoint = matches
+            .value_of("checkpoint")
+            .map(String::from)
+            .or_else(|| resume.clone());
+        let checkpoint_seconds = matches
+            .value_of("checkpoint seconds")
+            .and_then(|s| s.parse().ok())
             .unwrap();
 
-        let output_file = matches.value_of("output path").unwrap().to_owned();
+        let sky = !given("no sky") && settings.sky.unwrap_or(true);
+
+        let bvh_split_method = matches
+            .value_of("bvh split method")
+            .and_then(|m| m.parse().ok())
+            .unwrap();
 
         Self {
             img_width: width,
             img_height: height,
             samples_per_pixel,
+            adaptive,
+            heatmap,
             max_ray_depth,
+            seed,
             output_file,
+            tone_mapping,
+            aovs,
+            aov_layers,
+            denoise,
+            save_raw,
+            progressive,
+            checkpoint,
+            checkpoint_seconds,
+            resume,
+            sky,
+            bvh_split_method,
+            scene,
         }
     }
 }
@@ -1,12 +1,46 @@
 mod from_args;
 
+use crate::aov::Aov;
+use crate::bvh::SplitMethod;
+use crate::output::ToneMapping;
+use crate::render::{Adaptive, Progressive};
+use crate::scene::SceneDescription;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub img_width: usize,
     pub img_height: usize,
+    /// The samples of every pixel, or with adaptive sampling the most a pixel gets
     pub samples_per_pixel: usize,
+    /// Stop sampling the pixels once they aren't noisy
+    pub adaptive: Option<Adaptive>,
+    /// Also write a picture of the samples spent per pixel
+    pub heatmap: bool,
     pub max_ray_depth: usize,
+    /// With the pixel and the index of the sample it decides the random numbers of a sample
+    pub seed: u64,
     pub output_file: String,
+    pub tone_mapping: ToneMapping,
+    /// Written next to the picture, or into it with `aov_layers`
+    pub aovs: Vec<Aov>,
+    /// The AOVs are layers of the EXR picture instead of files of their own
+    pub aov_layers: bool,
+    /// Filter the noise out of the picture, guided by what the camera rays hit
+    pub denoise: bool,
+    /// Also write the picture before the denoiser
+    pub save_raw: bool,
+    /// Render one sample per pixel at a time and write snapshots, `None` renders tile by tile
+    pub progressive: Option<Progressive>,
+    /// Where the state of the render is saved, so it can be continued
+    pub checkpoint: Option<String>,
+    /// Time between the checkpoints if the render isn't progressive, those write them with the snapshots
+    pub checkpoint_seconds: f64,
+    /// The checkpoint the render continues from
+    pub resume: Option<String>,
+    /// Without the sky the environment is black, only the emissive materials give light
+    pub sky: bool,
+    pub bvh_split_method: SplitMethod,
+    pub scene: SceneDescription,
 }
 
 impl Config {
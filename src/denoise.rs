@@ -0,0 +1,241 @@
+use super::{Color, Film};
+
+/// The B3 spline, the taps of the filter on each axis
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Below this the albedo isn't divided out, the color is filtered as it is
+const MIN_ALBEDO: f64 = 0.01;
+
+/// What the camera rays hit first, they tell the denoiser where the edges are
+#[derive(Debug, Clone)]
+pub struct Guides {
+    pub albedo: Film,
+    pub normal: Film,
+    pub depth: Film,
+}
+
+/// Edge-avoiding à-trous wavelet filter, the spatial part of SVGF
+///
+/// The same 5x5 kernel is applied with growing gaps between its taps,
+/// each pass reaches twice as far as the one before.
+/// The taps are weighted down across the edges of the guides and of the color.
+/// The albedo is divided out first, so the textures aren't blurred, only the lighting
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Denoiser {
+    /// Number of passes, the filter reaches 2 * 2^iterations pixels
+    pub iterations: usize,
+    /// Allowed difference of the tone compressed colors, halved after each pass
+    pub color_sigma: f64,
+    /// Exponent of the cosine between the normals
+    pub normal_power: f64,
+    /// Allowed relative difference of the depths for each pixel of distance
+    pub depth_sigma: f64,
+    /// Allowed difference of the albedos
+    pub albedo_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            color_sigma: 0.5,
+            normal_power: 64.0,
+            depth_sigma: 0.05,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn apply(&self, film: &Film, guides: &Guides) -> Film {
+        let (width, height) = (film.width(), film.height());
+        let albedo = |x, y| {
+            let a = guides.albedo.pixel(x, y);
+            let channel = |c: f64| if c > MIN_ALBEDO { c } else { 1.0 };
+            Color::new(channel(a[0]), channel(a[1]), channel(a[2]))
+        };
+
+        let mut lighting: Vec<Color> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                film.pixel(x, y) / albedo(x, y)
+            })
+            .collect();
+
+        let mut color_sigma = self.color_sigma;
+        for iteration in 0..self.iterations {
+            lighting = self.pass(
+                &lighting,
+                width,
+                height,
+                1 << iteration,
+                color_sigma,
+                guides,
+            );
+            color_sigma /= 2.0;
+        }
+
+        Film::from_fn(width, height, |x, y| lighting[y * width + x] * albedo(x, y))
+    }
+
+    /// One pass of the filter with `step` pixels between the taps
+    fn pass(
+        &self,
+        lighting: &[Color],
+        width: usize,
+        height: usize,
+        step: usize,
+        color_sigma: f64,
+        guides: &Guides,
+    ) -> Vec<Color> {
+        // Bright lights would outweigh everything else, the differences are taken after x / (1 + x)
+        let compress = |c: Color| {
+            Color::new(
+                c[0] / (1.0 + c[0]),
+                c[1] / (1.0 + c[1]),
+                c[2] / (1.0 + c[2]),
+            )
+        };
+        let squared = |c: Color| c[0] * c[0] + c[1] * c[1] + c[2] * c[2];
+
+        let mut filtered = Vec::with_capacity(lighting.len());
+        for y in 0..height {
+            for x in 0..width {
+                let color = compress(lighting[y * width + x]);
+                let normal = unit(guides.normal.pixel(x, y));
+                let depth = guides.depth.pixel(x, y)[0];
+                let albedo = guides.albedo.pixel(x, y);
+
+                let mut sum = Color::black();
+                let mut total_weight = 0.0;
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    let qy = y as isize + (j as isize - 2) * step as isize;
+                    if qy < 0 || qy >= height as isize {
+                        continue;
+                    }
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let qx = x as isize + (i as isize - 2) * step as isize;
+                        if qx < 0 || qx >= width as isize {
+                            continue;
+                        }
+                        let (qx, qy) = (qx as usize, qy as usize);
+                        let q = lighting[qy * width + qx];
+
+                        let color_weight =
+                            -squared(compress(q) - color) / (color_sigma * color_sigma);
+
+                        let normal_weight = match (normal, unit(guides.normal.pixel(qx, qy))) {
+                            (Some(n), Some(q_normal)) => {
+                                let cosine = (0..3).map(|k| n[k] * q_normal[k]).sum::<f64>();
+                                cosine.max(0.0).powf(self.normal_power)
+                            }
+                            // Both missed the scene
+                            (None, None) => 1.0,
+                            _ => 0.0,
+                        };
+
+                        // Relative to the distance, and to how far the tap is on the screen
+                        let q_depth = guides.depth.pixel(qx, qy)[0];
+                        let distance = ((i as f64 - 2.0).powi(2) + (j as f64 - 2.0).powi(2)).sqrt();
+                        let depth_scale =
+                            self.depth_sigma * depth.max(q_depth) * step as f64 * distance;
+                        let depth_weight = if depth == q_depth {
+                            0.0
+                        } else if depth_scale > 0.0 {
+                            -(depth - q_depth).abs() / depth_scale
+                        } else {
+                            f64::NEG_INFINITY
+                        };
+
+                        let albedo_weight = -squared(guides.albedo.pixel(qx, qy) - albedo)
+                            / (self.albedo_sigma * self.albedo_sigma);
+
+                        let weight = kx
+                            * ky
+                            * normal_weight
+                            * (color_weight + depth_weight + albedo_weight).exp();
+                        sum += weight * q;
+                        total_weight += weight;
+                    }
+                }
+                // The pixel itself always has some weight
+                filtered.push(sum * (1.0 / total_weight));
+            }
+        }
+        filtered
+    }
+}
+
+/// The averaged normals are shorter at the edges, `None` where every sample missed
+fn unit(normal: Color) -> Option<Color> {
+    let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    if length > 0.0 {
+        Some(normal * (1.0 / length))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn flat_guides(width: usize, height: usize) -> Guides {
+        Guides {
+            albedo: Film::from_fn(width, height, |_, _| Color::new(0.5, 0.5, 0.5)),
+            normal: Film::from_fn(width, height, |_, _| Color::new(0.0, 0.0, 1.0)),
+            depth: Film::from_fn(width, height, |_, _| Color::new(2.0, 2.0, 2.0)),
+        }
+    }
+
+    fn variance(film: &Film) -> (f64, f64) {
+        let values: Vec<f64> = film.rows().flatten().map(|c| c[1]).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        (mean, variance)
+    }
+
+    #[test]
+    fn noise_is_smoothed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let noisy = Film::from_fn(32, 32, |_, _| {
+            let v = rng.gen_range(0.1, 0.3);
+            Color::new(v, v, v)
+        });
+        let denoised = Denoiser::default().apply(&noisy, &flat_guides(32, 32));
+
+        let (noisy_mean, noisy_variance) = variance(&noisy);
+        let (mean, variance) = variance(&denoised);
+        assert!((mean - noisy_mean).abs() < 0.01);
+        assert!(
+            variance < noisy_variance / 10.0,
+            "{} {}",
+            variance,
+            noisy_variance
+        );
+    }
+
+    #[test]
+    fn edges_of_the_normals_are_kept() {
+        let mut guides = flat_guides(16, 16);
+        guides.normal = Film::from_fn(16, 16, |x, _| {
+            if x < 8 {
+                Color::new(0.0, 0.0, 1.0)
+            } else {
+                Color::new(1.0, 0.0, 0.0)
+            }
+        });
+        // The same brightness would still blend, only the normals separate the sides
+        let film = Film::from_fn(16, 16, |x, _| {
+            if x < 8 {
+                Color::new(0.2, 0.2, 0.2)
+            } else {
+                Color::new(0.25, 0.25, 0.25)
+            }
+        });
+        let denoised = Denoiser::default().apply(&film, &guides);
+        assert!((denoised.pixel(7, 8)[0] - 0.2).abs() < 1e-6);
+        assert!((denoised.pixel(8, 8)[0] - 0.25).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,134 @@
+/// Samples the [0, 1) interval proportionally to a piecewise constant function
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    /// The function value of each equal piece
+    function: Vec<f64>,
+    /// Running integral at the start of each piece, with the total at the end
+    cdf: Vec<f64>,
+}
+
+impl Distribution1D {
+    /// The values must be non negative
+    pub fn new(function: Vec<f64>) -> Self {
+        let n = function.len() as f64;
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for value in &function {
+            cdf.push(cdf.last().unwrap() + value / n);
+        }
+        Self { function, cdf }
+    }
+
+    /// Integral of the function over [0, 1)
+    pub fn integral(&self) -> f64 {
+        *self.cdf.last().unwrap()
+    }
+
+    /// A random point for the uniform random `u`, the density there and the index of its piece
+    ///
+    /// Uniform if the function is zero everywhere
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.function.len();
+        if self.integral() <= 0.0 {
+            let index = ((u * n as f64) as usize).min(n - 1);
+            return (u, 1.0, index);
+        }
+        let target = u * self.integral();
+        // The last piece which starts before the target
+        let index = (self.cdf.partition_point(|&c| c <= target) - 1).min(n - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            (target - self.cdf[index]) / width
+        } else {
+            0.0
+        };
+        let x = (index as f64 + offset) / n as f64;
+        (x, self.function[index] / self.integral(), index)
+    }
+
+    /// The density of `sample` returning `x`
+    pub fn pdf(&self, x: f64) -> f64 {
+        let n = self.function.len();
+        if self.integral() <= 0.0 {
+            return 1.0;
+        }
+        let index = ((x * n as f64) as usize).min(n - 1);
+        self.function[index] / self.integral()
+    }
+}
+
+/// Samples the unit square proportionally to a piecewise constant function on a grid
+///
+/// A row is chosen by the marginal distribution, then a column in that row
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// The values are given row by row
+    pub fn new(function: &[f64], width: usize, height: usize) -> Self {
+        let rows: Vec<_> = function
+            .chunks(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(Distribution1D::integral).collect());
+        Self { rows, marginal }
+    }
+
+    /// A random (x, y) point for the uniform random `u`, `v`, and the density there
+    pub fn sample(&self, u: f64, v: f64) -> ((f64, f64), f64) {
+        let (y, row_pdf, row) = self.marginal.sample(v);
+        let (x, column_pdf, _) = self.rows[row].sample(u);
+        ((x, y), row_pdf * column_pdf)
+    }
+
+    /// The density of `sample` returning (x, y)
+    pub fn pdf(&self, x: f64, y: f64) -> f64 {
+        let row = ((y * self.rows.len() as f64) as usize).min(self.rows.len() - 1);
+        self.marginal.pdf(y) * self.rows[row].pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_follow_the_function() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0]);
+        assert!((distribution.integral() - 4.0 / 3.0).abs() < 1e-12);
+        for i in 0..1000 {
+            let (x, pdf, index) = distribution.sample(i as f64 / 1000.0);
+            assert_ne!(index, 1, "an empty piece was sampled");
+            assert!((0.0..1.0).contains(&x));
+            assert!((distribution.pdf(x) - pdf).abs() < 1e-12);
+        }
+        // A quarter of the samples fall into the first piece
+        let (x, _, index) = distribution.sample(0.125);
+        assert_eq!(index, 0);
+        assert!((x - 1.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn the_density_integrates_to_one() {
+        let function = [0.0, 1.0, 2.0, 5.0, 0.5, 0.0];
+        let distribution = Distribution2D::new(&function, 3, 2);
+        let n = 300;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let (x, y) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                integral += distribution.pdf(x, y) / (n * n) as f64;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-9);
+
+        let ((x, y), pdf) = distribution.sample(0.5, 0.1);
+        assert!((distribution.pdf(x, y) - pdf).abs() < 1e-12);
+        // The brightest cell is the most likely
+        assert!((distribution.pdf(0.1, 0.9) - 5.0 / (8.5 / 6.0)).abs() < 1e-12);
+    }
+}
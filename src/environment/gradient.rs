@@ -0,0 +1,50 @@
+use super::Environment;
+use crate::{Color, Point};
+
+/// A sky blending from the horizon to the zenith
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gradient {
+    bottom: Color,
+    top: Color,
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Default for Gradient {
+    /// White to light blue
+    fn default() -> Self {
+        Self::new(Color::white(), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Environment for Gradient {
+    fn color(&self, direction: &Point) -> Color {
+        // Based on the y coordinate
+        // t=0 => bottom
+        // t=1 => top
+        let t = 0.5 * (direction.unit_vector().y() + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
+
+/// The same light from every direction, black for scenes lit only by their lights
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Uniform {
+    color: Color,
+}
+
+impl Uniform {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Environment for Uniform {
+    fn color(&self, _direction: &Point) -> Color {
+        self.color
+    }
+}
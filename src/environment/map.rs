@@ -0,0 +1,167 @@
+use image::codecs::hdr::HdrDecoder;
+use std::{f64::consts::PI, fs::File, io::BufReader, path::Path};
+
+use super::{Distribution2D, Environment};
+use crate::{Color, Point, Sampler};
+
+/// An equirectangular picture of the surroundings
+///
+/// The columns go around the y axis, the rows go from the top (+y) to the bottom (-y).
+/// Bright parts, like the sun, are sampled more often by the shadow rays
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// Row by row, from the top
+    pixels: Vec<Color>,
+    /// Turn around the y axis in degrees
+    rotation: f64,
+    /// Multiplies the pixels
+    intensity: f64,
+    /// Over the texture coordinates, proportional to the luminance
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        // The rows near the poles are squeezed into less solid angle
+        let luminance: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, color)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                color.luminance() * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(&luminance, width, height);
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            distribution,
+        }
+    }
+
+    /// Read a Radiance `.hdr` file
+    pub fn load(path: &Path) -> Result<Self, image::ImageError> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+        Ok(Self::new(
+            metadata.width as usize,
+            metadata.height as usize,
+            pixels,
+        ))
+    }
+
+    pub fn with_rotation(mut self, degrees: f64) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Texture coordinates of the direction on the picture
+    fn uv(&self, direction: &Point) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+        let phi = (-d.z()).atan2(d.x()) + PI;
+        let u = (phi / (2.0 * PI) - self.rotation / 360.0).rem_euclid(1.0);
+        (u, theta / PI)
+    }
+
+    /// Direction of the texture coordinates, and the sine of its angle from the y axis
+    fn direction(&self, u: f64, v: f64) -> (Point, f64) {
+        let theta = v * PI;
+        let phi = 2.0 * PI * (u + self.rotation / 360.0) - PI;
+        let sin_theta = theta.sin();
+        let direction = Point::new(sin_theta * phi.cos(), theta.cos(), -sin_theta * phi.sin());
+        (direction, sin_theta)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn color(&self, direction: &Point) -> Color {
+        let (u, v) = self.uv(direction);
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.intensity * self.pixels[y * self.width + x]
+    }
+
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, direction: &Point) -> f64 {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // The picture is stretched over 2π by π radians
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random(&self, sampler: &mut Sampler) -> Option<Point> {
+        let ((u, v), _) = self
+            .distribution
+            .sample(sampler.next_f64(), sampler.next_f64());
+        Some(self.direction(u, v).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_round_trip() {
+        let map = EnvironmentMap::new(4, 2, vec![Color::white(); 8]).with_rotation(30.0);
+        for &(u, v) in &[(0.1, 0.2), (0.6, 0.5), (0.95, 0.9)] {
+            let (direction, _) = map.direction(u, v);
+            let (u2, v2) = map.uv(&direction);
+            assert!((u - u2).abs() < 1e-9 && (v - v2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn bright_pixels_are_sampled() {
+        // A dark sky with one bright pixel
+        let mut pixels = vec![Color::new(0.01, 0.01, 0.01); 8 * 4];
+        pixels[8 + 5] = Color::new(100.0, 100.0, 100.0);
+        let map = EnvironmentMap::new(8, 4, pixels).with_intensity(2.0);
+
+        let mut bright = 0;
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..1000 {
+            let direction = map.random(&mut sampler).unwrap();
+            assert!(map.pdf_value(&direction) > 0.0);
+            if map.color(&direction)[0] == 200.0 {
+                bright += 1;
+            }
+        }
+        assert!(bright > 900);
+
+        // The density integrates to one over the sphere
+        let n = 200;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let (u, v) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                let (direction, sin_theta) = map.direction(u, v);
+                let solid_angle = 2.0 * PI * PI * sin_theta / (n * n) as f64;
+                integral += map.pdf_value(&direction) * solid_angle;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,39 @@
+mod distribution;
+mod gradient;
+mod map;
+mod sky;
+
+use std::fmt::Debug;
+use std::marker::{Send, Sync};
+
+use crate::{Color, Point, Sampler};
+
+/// The light coming from infinitely far away, where the rays go if they don't hit anything
+pub trait Environment: Debug + Sync + Send {
+    /// The light coming from the direction
+    fn color(&self, direction: &Point) -> Color;
+
+    /// Whether the light is bright enough in some directions to send shadow rays there
+    ///
+    /// Otherwise it is only found by the scattered rays
+    fn is_sampled(&self) -> bool {
+        false
+    }
+
+    /// Density of `random` choosing the direction
+    fn pdf_value(&self, _direction: &Point) -> f64 {
+        0.0
+    }
+
+    /// A random direction, chosen proportionally to the light coming from there
+    ///
+    /// `None` if the environment isn't sampled
+    fn random(&self, _sampler: &mut Sampler) -> Option<Point> {
+        None
+    }
+}
+
+pub use distribution::Distribution2D;
+pub use gradient::{Gradient, Uniform};
+pub use map::EnvironmentMap;
+pub use sky::PhysicalSky;
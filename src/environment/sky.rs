@@ -0,0 +1,233 @@
+use std::f64::consts::PI;
+
+use super::Environment;
+use crate::{Color, Onb, Point, Sampler};
+
+/// Angular radius of the sun seen from the earth
+const SUN_RADIUS: f64 = 0.004_65;
+
+/// Luminance in kcd/m² is scaled by this, so a clear zenith at noon is about 1
+const LUMINANCE_SCALE: f64 = 0.05;
+
+/// Radiance of the sun above the atmosphere, in the same units
+const SUN_RADIANCE: f64 = 80_000.0;
+
+/// The Perez formula's coefficients of one of the Y, x, y components
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Perez([f64; 5]);
+
+impl Perez {
+    /// Relative brightness at the angle `theta` from the zenith and `gamma` from the sun
+    fn f(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.0;
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+/// Clear daylight sky of the Preetham model with the sun in it
+///
+/// Turbidity is the haziness of the air, 2 is very clear, 10 is hazy.
+/// Below the horizon the color of the horizon continues, the scene needs a ground
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalSky {
+    /// Unit vector towards the sun
+    sun: Point,
+    /// Angle of the sun from the zenith
+    sun_theta: f64,
+    /// Y, x and y at the zenith
+    zenith: [f64; 3],
+    perez: [Perez; 3],
+    sun_color: Color,
+    intensity: f64,
+}
+
+impl PhysicalSky {
+    /// The sun has to be above the horizon (y > 0)
+    pub fn new(sun_direction: Point, turbidity: f64) -> Self {
+        let sun = sun_direction.unit_vector();
+        let sun_theta = sun.y().clamp(0.0, 1.0).acos();
+        let t = turbidity;
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * sun_theta);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |coefficients: [[f64; 4]; 3]| {
+            let powers = [sun_theta.powi(3), sun_theta.powi(2), sun_theta, 1.0];
+            let row = |c: [f64; 4]| (0..4).map(|i| c[i] * powers[i]).sum::<f64>();
+            t * t * row(coefficients[0]) + t * row(coefficients[1]) + row(coefficients[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        Self {
+            sun,
+            sun_theta,
+            zenith: [zenith_luminance.max(0.0), zenith_x, zenith_y],
+            perez,
+            sun_color: sun_color(sun_theta, turbidity),
+            intensity: 1.0,
+        }
+    }
+
+    /// Multiplies the sky and the sun
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// The color of the sky without the sun
+    fn sky(&self, direction: &Point) -> Color {
+        // Just above the horizon, the formula blows up at it and isn't defined below
+        let cos_theta = direction.y().max(0.001);
+        let gamma = Point::dot(direction, &self.sun).clamp(-1.0, 1.0).acos();
+
+        let mut yxy = [0.0; 3];
+        for (i, perez) in self.perez.iter().enumerate() {
+            yxy[i] = self.zenith[i] * perez.f(cos_theta, gamma) / perez.f(1.0, self.sun_theta);
+        }
+        let [luminance, x, y] = yxy;
+        xyy_to_rgb(x, y, LUMINANCE_SCALE * luminance)
+    }
+
+    /// Cosine of the angular radius of the sun
+    fn cos_sun_radius() -> f64 {
+        SUN_RADIUS.cos()
+    }
+}
+
+impl Environment for PhysicalSky {
+    fn color(&self, direction: &Point) -> Color {
+        let direction = direction.unit_vector();
+        let mut color = self.sky(&direction);
+        if Point::dot(&direction, &self.sun) >= Self::cos_sun_radius() {
+            color += self.sun_color;
+        }
+        self.intensity * color
+    }
+
+    /// Only the sun is sampled, the rest of the sky is found by scattering
+    fn is_sampled(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, direction: &Point) -> f64 {
+        if Point::dot(&direction.unit_vector(), &self.sun) >= Self::cos_sun_radius() {
+            1.0 / (2.0 * PI * (1.0 - Self::cos_sun_radius()))
+        } else {
+            0.0
+        }
+    }
+
+    /// Uniform over the sun disk
+    fn random(&self, sampler: &mut Sampler) -> Option<Point> {
+        let (r1, r2) = (sampler.next_f64(), sampler.next_f64());
+        let z = 1.0 + r2 * (Self::cos_sun_radius() - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        let local = Point::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+        Some(Onb::from_w(&self.sun).local(&local))
+    }
+}
+
+/// The sun seen through the atmosphere, reddened by the air and the haze
+///
+/// Uses the Rayleigh and the Ångström optical depths at red, green and blue wavelengths
+fn sun_color(sun_theta: f64, turbidity: f64) -> Color {
+    // Relative optical mass of the air, longer near the horizon
+    let degrees = sun_theta.to_degrees().min(93.0);
+    let air_mass = 1.0 / (sun_theta.cos() + 0.15 * (93.885 - degrees).powf(-1.253));
+    let beta = 0.046_08 * turbidity - 0.045_86;
+
+    let transmittance = |micrometers: f64| {
+        let rayleigh = 0.008_735 * micrometers.powf(-4.08);
+        let aerosol = beta * micrometers.powf(-1.3);
+        (-air_mass * (rayleigh + aerosol)).exp()
+    };
+    SUN_RADIANCE
+        * Color::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        )
+}
+
+/// CIE xyY to linear sRGB
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::black();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    let big_y = luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blue_sky_and_yellow_sun() {
+        let sky = PhysicalSky::new(Point::new(1.0, 1.0, 0.0), 3.0);
+        let zenith = sky.color(&Point::new(0.0, 1.0, 0.0));
+        assert!(zenith[2] > zenith[0], "the sky isn't blue: {:?}", zenith);
+        assert!(zenith[1] > 0.1 && zenith[1] < 10.0);
+        // No black gap between the sky and the ground
+        assert!(sky.color(&Point::new(0.0, -1.0, 1.0))[2] > 0.1);
+
+        let sun = sky.color(&Point::new(1.0, 1.0, 0.0));
+        assert!(sun[0] > sun[2] && sun[2] > 100.0 * zenith[2]);
+
+        // The lower sun is redder
+        let low = PhysicalSky::new(Point::new(1.0, 0.1, 0.0), 3.0).sun_color;
+        assert!(low[2] / low[0] < sky.sun_color[2] / sky.sun_color[0]);
+    }
+
+    #[test]
+    fn sampled_directions_hit_the_sun() {
+        let sky = PhysicalSky::new(Point::new(0.3, 0.8, -0.5), 2.5);
+        let solid_angle = 2.0 * PI * (1.0 - SUN_RADIUS.cos());
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..1000 {
+            let direction = sky.random(&mut sampler).unwrap();
+            assert!((sky.pdf_value(&direction) - 1.0 / solid_angle).abs() < 1e-6);
+            assert!(sky.color(&direction)[0] > 1000.0);
+        }
+        assert_eq!(sky.pdf_value(&Point::new(0.0, 1.0, 0.0)), 0.0);
+    }
+}
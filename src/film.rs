@@ -0,0 +1,45 @@
+use super::Color;
+
+/// The rendered picture in linear color, before it is encoded into a file
+///
+/// The rows go from the top to the bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Film {
+    /// Calculate the color of each pixel, row by row from the top
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> Color) -> Self {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(f(x, y));
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// The rows from the top
+    pub fn rows(&self) -> impl Iterator<Item = &[Color]> {
+        self.pixels.chunks(self.width)
+    }
+}
@@ -9,6 +9,11 @@ pub struct HitRecord {
     pub material: Arc<dyn Material>,
     pub t: f64,
     pub front_face: bool,
+    /// Texture coordinates
+    pub u: f64,
+    pub v: f64,
+    /// Which object of the scene was hit, 0 if the object has no id
+    pub object_id: u32,
 }
 
 impl HitRecord {
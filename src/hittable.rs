@@ -1,7 +1,49 @@
-use super::{HitRecord, Ray};
+use super::{Aabb, HitRecord, Point, Ray, Sampler};
 use std::fmt::Debug;
+use std::marker::{Send, Sync};
+use std::sync::Arc;
 
 /// An object that the ray can hit
-pub trait Hittable: Debug {
+pub trait Hittable: Debug + Sync + Send {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// The smallest box which contains the whole object
+    ///
+    /// Returns `None` if the object is unbounded
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// The probability density (per solid angle) of `random` choosing `direction` from `origin`
+    /// at `time`, where the moving objects are
+    ///
+    /// Zero for the objects which can't be sampled as lights
+    fn pdf_value(&self, _origin: &Point, _direction: &Point, _time: f64) -> f64 {
+        0.0
+    }
+
+    /// A random direction from `origin` towards a point of the object
+    ///
+    /// Used to send shadow rays to the lights,
+    /// `None` for the objects which can't be sampled and when `pdf_value` would be zero everywhere
+    fn random(&self, _origin: &Point, _time: f64, _sampler: &mut Sampler) -> Option<Point> {
+        None
+    }
+}
+
+/// An object which is in the world and in the lights at the same time
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Point, time: f64) -> f64 {
+        (**self).pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point, time: f64, sampler: &mut Sampler) -> Option<Point> {
+        (**self).random(origin, time, sampler)
+    }
 }
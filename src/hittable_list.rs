@@ -1,25 +1,36 @@
-use super::Ray;
+use super::{Aabb, Point, Ray, Sampler};
 use super::{HitRecord, Hittable};
 
-use std::{
-    boxed::Box,
-    marker::{Send, Sync},
-};
+use std::boxed::Box;
 
 #[derive(Debug, Default)]
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable + Sync + Send>>,
+    objects: Vec<Box<dyn Hittable>>,
 }
 
 impl HittableList {
-    pub fn add(&mut self, obj: Box<dyn Hittable + Sync + Send>) {
+    pub fn add(&mut self, obj: Box<dyn Hittable>) {
         self.objects.push(obj)
     }
 
-    pub fn chain_add(mut self, obj: Box<dyn Hittable + Sync + Send>) -> Self {
+    pub fn chain_add(mut self, obj: Box<dyn Hittable>) -> Self {
         self.add(obj);
         self
     }
+
+    /// Move every object of the other list into this one
+    pub fn append(&mut self, other: HittableList) {
+        self.objects.extend(other.objects)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Take the objects out of the list
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
 }
 
 impl Hittable for HittableList {
@@ -30,4 +41,32 @@ impl Hittable for HittableList {
             obj.hit(ray, t_min, closest_so_far).or(best)
         })
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // An empty list or a single unbounded object makes the whole list unbounded
+        let mut boxes = self.objects.iter().map(|obj| obj.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, bbox| Some(Aabb::surrounding_box(&acc, &bbox?)))
+    }
+
+    /// Every object is chosen with the same probability
+    fn pdf_value(&self, origin: &Point, direction: &Point, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|obj| obj.pdf_value(origin, direction, time))
+            .sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: &Point, time: f64, sampler: &mut Sampler) -> Option<Point> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        let index = sampler.index(self.objects.len());
+        self.objects[index].random(origin, time, sampler)
+    }
 }
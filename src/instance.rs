@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use super::{Aabb, HitRecord, Hittable, Point, Ray, Sampler, Transform};
+
+/// A transformed copy of a shared object
+///
+/// Many instances can share the same object (like a mesh and its BVH),
+/// only the transform is stored for each of them
+#[derive(Debug)]
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    /// From object space to world space
+    transform: Transform,
+    /// Replaces the object id of the shared object's hits, unless it is 0
+    id: u32,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        Self {
+            object,
+            transform,
+            id: 0,
+        }
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // The direction is not normalized, so t is the same in both spaces
+        let to_object = self.transform.inverse();
+        let local = Ray::new(to_object.point(r.origin()), to_object.vector(r.direction()))
+            .with_time(r.time());
+
+        let mut hit = self.object.hit(&local, t_min, t_max)?;
+        hit.position = self.transform.point(&hit.position);
+        // Which side was hit doesn't change, the normal keeps facing the ray
+        hit.normal = self.transform.normal(&hit.normal).unit_vector();
+        if self.id != 0 {
+            hit.object_id = self.id;
+        }
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = self.object.bounding_box()?;
+        Some(self.transform.bounding_box(&bbox))
+    }
+
+    /// The density of the object in its own space, times the change of the solid angle
+    ///
+    /// A linear map `A` stretches the directions around `ω` by `|det A| / |A ω|³`,
+    /// which is 1 for rotations, translations and uniform scales
+    fn pdf_value(&self, origin: &Point, direction: &Point, time: f64) -> f64 {
+        let to_object = self.transform.inverse();
+        let local = to_object.vector(&direction.unit_vector());
+        let pdf = self
+            .object
+            .pdf_value(&to_object.point(origin), &local, time);
+        if pdf <= 0.0 {
+            return 0.0;
+        }
+        pdf * to_object.determinant().abs() / local.len().powi(3)
+    }
+
+    fn random(&self, origin: &Point, time: f64, sampler: &mut Sampler) -> Option<Point> {
+        let origin = self.transform.inverse().point(origin);
+        let direction = self.object.random(&origin, time, sampler)?;
+        Some(self.transform.vector(&direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color, Sphere};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn scaled_and_moved_sphere() {
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point::default(), 1.0, material));
+        let transform = Transform::scale(&Point::new(1.0, 2.0, 1.0))
+            .then(&Transform::translate(&Point::new(0.0, 0.0, -5.0)));
+        let instance = Instance::new(sphere.clone(), transform);
+
+        // The top of the stretched sphere
+        let down = Ray::new(Point::new(0.0, 10.0, -5.0), Point::new(0.0, -1.0, 0.0));
+        let hit = instance.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!((hit.position - Point::new(0.0, 2.0, -5.0)).near_zero());
+        assert!((hit.t - 8.0).abs() < 1e-9);
+        assert!(hit.front_face);
+        assert!((hit.normal - Point::new(0.0, 1.0, 0.0)).near_zero());
+
+        // The original is not moved
+        assert!(sphere.hit(&down, 0.001, f64::INFINITY).is_none());
+
+        let bbox = instance.bounding_box().unwrap();
+        assert_eq!(
+            bbox,
+            Aabb::new(Point::new(-1.0, -2.0, -6.0), Point::new(1.0, 2.0, -4.0))
+        );
+    }
+
+    #[test]
+    fn sampled_like_a_sphere_in_world_space() {
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point::default(), 1.0, material));
+        let origin = Point::default();
+        let mut sampler = Sampler::new(0, 0, 0);
+
+        // Uniformly scaled and moved, the same as a bigger sphere there
+        let transform = Transform::scale(&Point::new(2.0, 2.0, 2.0))
+            .then(&Transform::translate(&Point::new(0.0, 0.0, -5.0)));
+        let instance = Instance::new(sphere.clone(), transform);
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let expected = Sphere::new(Point::new(0.0, 0.0, -5.0), 2.0, material);
+        for _ in 0..100 {
+            let direction = instance.random(&origin, 0.0, &mut sampler).unwrap();
+            assert!(instance
+                .hit(&Ray::new(origin, direction), 0.001, f64::INFINITY)
+                .is_some());
+            let pdf = instance.pdf_value(&origin, &direction, 0.0);
+            assert!((pdf - expected.pdf_value(&origin, &direction, 0.0)).abs() < 1e-9);
+        }
+
+        // Stretched, the density still integrates to 1 over all the directions
+        let transform = Transform::scale(&Point::new(1.0, 3.0, 0.5))
+            .then(&Transform::rotate(&Point::new(1.0, 0.0, 1.0), 30.0))
+            .then(&Transform::translate(&Point::new(0.5, 0.0, -2.0)));
+        let instance = Instance::new(sphere, transform);
+        let samples = 200_000;
+        let integral: f64 = (0..samples)
+            .map(|_| {
+                let direction = Point::random_unit_vec(&mut sampler);
+                instance.pdf_value(&origin, &direction, 0.0) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / samples as f64;
+        assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the first Ctrl+C
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch Ctrl+C, so the render can stop and save what it has
+///
+/// The second Ctrl+C exits right away.
+/// Does nothing on platforms other than unix, Ctrl+C just exits there
+pub fn install() {
+    #[cfg(unix)]
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = on_signal;
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+/// Whether Ctrl+C was pressed since `install`
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_: libc::c_int) {
+    if REQUESTED.swap(true, Ordering::Relaxed) {
+        // Only async-signal-safe calls are allowed here
+        unsafe { libc::_exit(130) }
+    }
+}
@@ -1,4 +1,3 @@
-#[macro_use]
 macro_rules! impl_immutable_op {
     ($target:ident, Neg) => {
         impl std::ops::Neg for $target {
@@ -73,7 +72,6 @@ macro_rules! impl_immutable_op {
     };
 }
 
-#[macro_use]
 macro_rules! impl_assign_op {
     ($target:ident, $trait_name:ident, $trait_fn:ident) => {
         impl $trait_name<Self> for $target {
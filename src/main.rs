@@ -1,30 +1,62 @@
 #[macro_use]
 mod macros;
 
+mod aabb;
+mod aov;
+mod bvh;
 mod camera;
+mod checkpoint;
 mod color;
 mod config;
+mod denoise;
+mod environment;
+mod film;
 mod hit_record;
 mod hittable;
 mod hittable_list;
+mod instance;
+mod interrupt;
 mod material;
+mod mesh;
+mod moving;
+mod onb;
+mod output;
 mod point;
 mod ray;
+mod render;
+mod sampler;
+mod scene;
 mod sphere;
+mod texture;
+mod transform;
+mod triangle;
+mod world;
 
-use ::rand::random;
+use aabb::Aabb;
+use aov::Aov;
+use bvh::Bvh;
 use camera::Camera;
+use checkpoint::Checkpoint;
 use color::Color;
 use config::Config;
+use denoise::{Denoiser, Guides};
+use environment::{Environment, Uniform};
+use film::Film;
 use hit_record::HitRecord;
 use hittable::Hittable;
 use hittable_list::HittableList;
-use image::{ImageBuffer, Rgb};
+use instance::Instance;
+use moving::Moving;
+use onb::Onb;
 use point::Point;
 use ray::Ray;
-use rayon::prelude::*;
+use render::{Pixel, Renderer};
+use sampler::Sampler;
 use sphere::Sphere;
-use std::{boxed::Box, sync::Arc};
+use std::{path::Path, time::Instant};
+use transform::Transform;
+use triangle::Triangle;
+use world::World;
 
 fn main() {
     eprintln!("Loading config...");
@@ -37,75 +69,209 @@ fn main() {
     eprintln!(" width:            {}px", img_width);
     eprintln!(" height:           {}px", img_height);
     eprintln!(" antialias level:  {}", config.samples_per_pixel);
+    if let Some(adaptive) = &config.adaptive {
+        eprintln!(
+            " adaptive:         {} error, {} to {} samples",
+            adaptive.threshold, adaptive.min_samples, config.samples_per_pixel
+        );
+    }
     eprintln!(" ray depth:        {}", config.max_ray_depth);
+    eprintln!(" seed:             {}", config.seed);
     eprintln!(" output file:      {}", config.output_file);
-    eprintln!();
+    eprintln!(
+        " tone mapping:     {} at {:+} stops",
+        config.tone_mapping.operator, config.tone_mapping.exposure
+    );
+    if !config.aovs.is_empty() {
+        let names: Vec<_> = config.aovs.iter().map(|aov| aov.to_string()).collect();
+        let place = if config.aov_layers { "layers" } else { "files" };
+        eprintln!(" aovs:             {} ({})", names.join(", "), place);
+    }
+    if config.denoise {
+        eprintln!(" denoiser:         on");
+    }
+    eprintln!(
+        " sky:              {}",
+        if config.sky { "on" } else { "off" }
+    );
 
     // World
-    let world = {
-        let material_ground = Arc::new(material::Lambertian::new(Color::new(0.8, 0.8, 0.0)));
-        let material_center = Arc::new(material::Lambertian::new(Color::new(0.7, 0.3, 0.3)));
-        let material_left = Arc::new(material::Metal::new(Color::new(0.8, 0.8, 0.8), 0.3));
-        let material_right = Arc::new(material::Metal::new(Color::new(0.8, 0.6, 0.2), 1.0));
-
-        HittableList::default()
-            .chain_add(Box::new(Sphere::new(
-                Point::new(0.0, -100.5, -1.0),
-                100.0,
-                material_ground.clone(),
-            )))
-            .chain_add(Box::new(Sphere::new(
-                Point::new(0.0, 0.0, -1.0),
-                0.5,
-                material_center.clone(),
-            )))
-            .chain_add(Box::new(Sphere::new(
-                Point::new(-1.0, 0.0, -1.0),
-                0.5,
-                material_left.clone(),
-            )))
-            .chain_add(Box::new(Sphere::new(
-                Point::new(1.0, 0.0, -1.0),
-                0.5,
-                material_right.clone(),
-            )))
+    let (objects, lights) = config.scene.build_world().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+
+    let bvh_build_start = Instant::now();
+    let bvh = Bvh::build(objects, config.bvh_split_method);
+    let bvh_build_time = bvh_build_start.elapsed();
+    let bvh_stats = bvh.stats();
+
+    eprintln!(" bvh split method: {}", config.bvh_split_method);
+    eprintln!(" bvh build time:   {:?}", bvh_build_time);
+    eprintln!(" bvh nodes:        {}", bvh_stats.nodes);
+    eprintln!(" bvh depth:        {}", bvh_stats.depth);
+    eprintln!();
+
+    let environment = if config.sky {
+        config.scene.build_environment().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        })
+    } else {
+        Box::new(Uniform::new(Color::black()))
+    };
+
+    let world = World {
+        objects: Box::new(bvh),
+        lights,
+        environment,
     };
 
     // Camera
-    let camera = Camera::new(Point::default(), config.aspect_ratio());
+    let camera = config
+        .scene
+        .camera(config.aspect_ratio(), world.objects.as_ref());
+    if config.scene.camera.autofocus {
+        eprintln!(" focus distance:   {:.3}", camera.focus_distance());
+        eprintln!();
+    }
 
     // Render
-    let mut bar = progress::Bar::new();
-    let mut prev_percent = 0;
-    bar.set_job_title("Rendering...");
-
-    let img = ImageBuffer::from_fn(img_width as _, img_height as _, |x, y| {
-        let y = img_height as u32 - 1 - y;
+    let renderer = Renderer {
+        camera: &camera,
+        world: &world,
+        width: img_width,
+        height: img_height,
+        max_ray_depth: config.max_ray_depth,
+        // Only found if there are AOVs to write or the denoiser needs them
+        find_first_hits: !config.aovs.is_empty() || config.denoise,
+        adaptive: config.adaptive,
+        seed: config.seed,
+    };
+    let output = Path::new(&config.output_file);
+    let beauty = |pixels: &[Pixel]| {
+        Film::from_fn(img_width, img_height, |x, y| {
+            pixels[y * img_width + x].average()
+        })
+    };
 
-        let percent = 100 - 100 * y as i32 / img_height as i32;
-        if prev_percent != percent {
-            bar.add_percent(percent - prev_percent);
+    let config_hash = checkpoint::config_hash(&config);
+    let mut pixels = match &config.resume {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(Path::new(path), img_width, img_height)
+                .unwrap_or_else(|err| {
+                    eprintln!("{}: {}", path, err);
+                    std::process::exit(1)
+                });
+            if checkpoint.config_hash != config_hash {
+                eprintln!(
+                    "{}: The checkpoint was made with other settings or another scene",
+                    path
+                );
+                std::process::exit(1)
+            }
+            checkpoint.pixels
         }
-        prev_percent = percent;
+        None => vec![Pixel::default(); img_width * img_height],
+    };
+    let save_checkpoint = |pixels: &[Pixel]| {
+        if let Some(path) = &config.checkpoint {
+            let checkpoint = Checkpoint {
+                config_hash,
+                width: img_width,
+                height: img_height,
+                pixels: pixels.to_vec(),
+            };
+            // A failed checkpoint doesn't stop the render, the next one may work
+            if let Err(err) = checkpoint.save(Path::new(path)) {
+                eprintln!("{}: {}", path, err);
+            }
+        }
+    };
 
-        let pixel_color = (0..config.samples_per_pixel)
-            .into_par_iter()
-            .map(|_| {
-                let u = (x as f64 + random::<f64>()) / (img_width as f64 - 1.0);
-                let v = (y as f64 + random::<f64>()) / (img_height as f64 - 1.0);
+    match config.progressive {
+        Some(options) => {
+            // The checkpoints are written with the snapshots
+            let snapshot = |pixels: &[Pixel]| {
+                // A failed snapshot doesn't stop the render, the next one may work
+                let film = beauty(pixels);
+                if let Err(err) = output::save(&film, output, &config.tone_mapping) {
+                    eprintln!("{}: {}", config.output_file, err);
+                }
+                save_checkpoint(pixels);
+            };
+            renderer.render_progressive(&mut pixels, config.samples_per_pixel, &options, snapshot);
+            if interrupt::requested() {
+                eprintln!(" interrupted, saving what was rendered");
+            }
+        }
+        None => {
+            let mut last_checkpoint = Instant::now();
+            renderer.render(&mut pixels, config.samples_per_pixel, |pixels| {
+                if last_checkpoint.elapsed().as_secs_f64() >= config.checkpoint_seconds {
+                    save_checkpoint(pixels);
+                    last_checkpoint = Instant::now();
+                }
+            });
+        }
+    }
+    save_checkpoint(&pixels);
 
-                // Create a ray pointing from the camera to (x, y)
-                let ray = camera.get_ray(u, v);
+    let samples: Vec<_> = pixels.iter().map(|pixel| pixel.samples).collect();
+    if config.adaptive.is_some() {
+        let average = samples.iter().sum::<usize>() as f64 / samples.len() as f64;
+        eprintln!(" average samples:  {:.1}", average);
+    }
+    if config.heatmap {
+        let path = output::heatmap_path(output);
+        let heatmap = output::heatmap(&samples, img_width, img_height, config.samples_per_pixel);
+        heatmap.save(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1)
+        });
+    }
+    let film = beauty(&pixels);
 
-                // Send the ray into the scene
-                return ray.color(&world, config.max_ray_depth);
-            })
-            .reduce(|| Color::black(), |a, b| a + b);
+    let aov_film = |aov| {
+        Film::from_fn(img_width, img_height, |x, y| {
+            let pixel = pixels[y * img_width + x];
+            pixel.first_hit.value(aov, pixel.samples.max(1))
+        })
+    };
+    let aovs: Vec<_> = config
+        .aovs
+        .iter()
+        .map(|&aov| (aov, aov_film(aov)))
+        .collect();
 
-        let color = pixel_color.rgb_bytes(config.samples_per_pixel);
+    let film = if config.denoise {
+        if config.save_raw {
+            let raw = output::raw_path(output);
+            output::save(&film, &raw, &config.tone_mapping).unwrap_or_else(|err| {
+                eprintln!("{}: {}", raw.display(), err);
+                std::process::exit(1)
+            });
+        }
+        eprintln!();
+        eprintln!("Denoising...");
+        let guides = Guides {
+            albedo: aov_film(Aov::Albedo),
+            normal: aov_film(Aov::Normal),
+            depth: aov_film(Aov::Depth),
+        };
+        Denoiser::default().apply(&film, &guides)
+    } else {
+        film
+    };
 
-        Rgb([color.0, color.1, color.2])
+    let saved = if config.aov_layers {
+        output::save_layers(&film, &aovs, output)
+    } else {
+        output::save(&film, output, &config.tone_mapping)
+            .and_then(|_| output::save_aovs(&aovs, output))
+    };
+    saved.unwrap_or_else(|err| {
+        eprintln!("{}: {}", config.output_file, err);
+        std::process::exit(1)
     });
-
-    img.save(config.output_file).unwrap();
 }
@@ -0,0 +1,117 @@
+use super::{Material, MaterialResult};
+use crate::{Color, HitRecord, Point, Ray, Sampler};
+
+/// A clear material, like glass or water
+///
+/// Rays are either reflected or refracted, the probability of reflection
+/// is given by Schlick's approximation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dielectric {
+    refraction_index: f64,
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        Self { refraction_index }
+    }
+
+    /// Schlick's approximation of the reflectance
+    fn reflectance(cosine: f64, ratio: f64) -> f64 {
+        let r0 = ((1.0 - ratio) / (1.0 + ratio)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<MaterialResult> {
+        // Entering the material from the air or leaving it
+        let ratio = if rec.front_face {
+            1.0 / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+
+        let unit_direction = r_in.direction().unit_vector();
+        let cos_theta = Point::dot(&-unit_direction, &rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
+
+        let total_internal_reflection = ratio * sin_theta > 1.0;
+        let direction = if total_internal_reflection
+            || Self::reflectance(cos_theta, ratio) > sampler.next_f64()
+        {
+            unit_direction.reflect(&rec.normal)
+        } else {
+            unit_direction.refract(&rec.normal, ratio)
+        };
+
+        Some(MaterialResult {
+            attenuation: Color::white(),
+            scattered: Ray::new(rec.position, direction).with_time(r_in.time()),
+            pdf: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Hittable, Sphere};
+    use std::sync::Arc;
+
+    #[test]
+    fn total_internal_reflection() {
+        let glass = Arc::new(Dielectric::new(1.5));
+        let sphere = Sphere::new(Point::default(), 1.0, glass.clone());
+
+        // From inside the sphere at a grazing angle
+        let ray = Ray::new(Point::new(0.9, 0.0, 0.0), Point::new(0.0, 1.0, 0.0));
+        let hit = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..100 {
+            let result = glass.scatter(&ray, &hit, &mut sampler).unwrap();
+            assert_eq!(result.attenuation, Color::white());
+            // Reflected back inside
+            assert!(Point::dot(result.scattered.direction(), &hit.normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn hollow_sphere_flips_the_ratio() {
+        let glass = Arc::new(Dielectric::new(1.5));
+        let hollow = Sphere::new(Point::default(), -1.0, glass.clone());
+
+        // The normal of a negative radius points inwards,
+        // so a ray coming from outside hits the back face
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Point::new(0.0, 0.0, -1.0));
+        let hit = hollow.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Point::new(0.0, 0.0, 1.0));
+
+        // Hitting it at 30 degrees, the refracted rays bend away from the normal
+        // like leaving glass: sin(theta') = 1.5 * sin(theta) = 0.75
+        let ray = Ray::new(Point::new(0.5, 0.0, 5.0), Point::new(0.0, 0.0, -1.0));
+        let hit = hollow.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+
+        let mut refracted = 0;
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..100 {
+            let result = glass.scatter(&ray, &hit, &mut sampler).unwrap();
+            let direction = result.scattered.direction().unit_vector();
+            if Point::dot(&direction, &hit.normal) < 0.0 {
+                let sin_theta = Point::cross(&direction, &hit.normal).len();
+                assert!((sin_theta - 0.75).abs() < 1e-9);
+                refracted += 1;
+            }
+        }
+        // The reflectance is about 5% at this angle
+        assert!(refracted > 50);
+    }
+}
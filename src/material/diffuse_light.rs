@@ -0,0 +1,64 @@
+use super::{Material, MaterialResult};
+use crate::{Color, HitRecord, Ray, Sampler};
+
+/// An area light, which emits the same light in every direction
+///
+/// Only the front face emits, so a light doesn't shine into the object it covers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut Sampler) -> Option<MaterialResult> {
+        None
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        if rec.front_face {
+            self.emit
+        } else {
+            Color::black()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Uniform;
+    use crate::{HittableList, Point, Sphere, World};
+    use std::sync::Arc;
+
+    #[test]
+    fn only_the_front_face_emits() {
+        let light = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        let world = World {
+            objects: Box::new(HittableList::default().chain_add(Box::new(Sphere::new(
+                Point::new(0.0, 0.0, -2.0),
+                0.5,
+                light,
+            )))),
+            lights: HittableList::default(),
+            environment: Box::new(Uniform::new(Color::black())),
+        };
+
+        let outside = Ray::new(Point::default(), Point::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            outside.color(&world, 10, &mut Sampler::new(0, 0, 0)),
+            Color::new(4.0, 4.0, 4.0)
+        );
+
+        let inside = Ray::new(Point::new(0.0, 0.0, -2.0), Point::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            inside.color(&world, 10, &mut Sampler::new(0, 0, 0)),
+            Color::black()
+        );
+    }
+}
@@ -1,32 +1,55 @@
 use super::{Material, MaterialResult};
-use crate::{Color, HitRecord, Point, Ray};
+use crate::texture::{SolidColor, Texture};
+use crate::{Color, HitRecord, Point, Ray, Sampler};
+use std::{f64::consts::PI, sync::Arc};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _: &Ray, rec: &HitRecord) -> Option<MaterialResult> {
-        let mut scatter_direction = rec.normal + Point::random_unit_vec();
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<MaterialResult> {
+        let mut scatter_direction = rec.normal + Point::random_unit_vec(sampler);
 
         // Prevent NaN issues
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal
         }
 
-        let attenuation = self.albedo;
-        let scattered = Ray::new(rec.position, scatter_direction);
+        let attenuation = self.albedo.value(rec.u, rec.v, &rec.position);
+        let scattered = Ray::new(rec.position, scatter_direction).with_time(r_in.time());
+        let pdf = self.scattering_pdf(r_in, rec, &scatter_direction);
 
         Some(MaterialResult {
             attenuation,
             scattered,
+            pdf: Some(pdf),
         })
     }
+
+    /// The directions are cosine weighted around the normal
+    fn scattering_pdf(&self, _: &Ray, rec: &HitRecord, direction: &Point) -> f64 {
+        let cosine = Point::dot(&rec.normal, &direction.unit_vector());
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.position)
+    }
 }
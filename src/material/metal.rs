@@ -1,33 +1,50 @@
 use super::{Material, MaterialResult};
-use crate::{Color, HitRecord, Point, Ray};
+use crate::texture::{SolidColor, Texture};
+use crate::{Color, HitRecord, Point, Ray, Sampler};
+use std::sync::Arc;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Metal {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
     fuzziness: f64,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzziness: f64) -> Self {
+        Self::textured(Arc::new(SolidColor::new(albedo)), fuzziness)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzziness: f64) -> Self {
         Self { albedo, fuzziness }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<MaterialResult> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<MaterialResult> {
         let reflected = r_in.direction().unit_vector().reflect(&rec.normal);
         let scattered = Ray::new(
             rec.position,
-            reflected + self.fuzziness * Point::random_in_unit_sphere(),
-        );
-        let attenuation = self.albedo;
-        if Point::dot(&scattered.direction(), &rec.normal) > 0.0 {
+            reflected + self.fuzziness * Point::random_in_unit_sphere(sampler),
+        )
+        .with_time(r_in.time());
+        let attenuation = self.albedo.value(rec.u, rec.v, &rec.position);
+        if Point::dot(scattered.direction(), &rec.normal) > 0.0 {
             Some(MaterialResult {
                 scattered,
                 attenuation,
+                pdf: None,
             })
         } else {
             None
         }
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.position)
+    }
 }
@@ -1,20 +1,46 @@
+mod dielectric;
+mod diffuse_light;
 mod lambertian;
 mod metal;
 
 use std::fmt::Debug;
 use std::marker::{Send, Sync};
 
-use crate::{color::Color, hit_record::HitRecord, ray::Ray};
+use crate::{color::Color, hit_record::HitRecord, point::Point, ray::Ray, Sampler};
 
 pub trait Material: Debug + Sync + Send {
-    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<MaterialResult>;
+    fn scatter(&self, r: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<MaterialResult>;
+
+    /// The probability density (per solid angle) of `scatter` choosing `direction`
+    ///
+    /// The reflected light is `attenuation * scattering_pdf * incoming / pdf`,
+    /// which lets the light sampling evaluate directions `scatter` didn't choose.
+    fn scattering_pdf(&self, _r: &Ray, _rec: &HitRecord, _direction: &Point) -> f64 {
+        0.0
+    }
+
+    /// The light given off by the surface, black for most materials
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::black()
+    }
+
+    /// The surface color without lighting, white if the material has none
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::white()
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub struct MaterialResult {
     pub attenuation: Color,
     pub scattered: Ray,
+    /// The density of the scattered direction
+    ///
+    /// `None` for mirror-like scattering, those directions can't come from light sampling
+    pub pdf: Option<f64>,
 }
 
+pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
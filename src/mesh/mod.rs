@@ -0,0 +1,214 @@
+mod mtl;
+pub mod obj;
+
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::triangle;
+use crate::{Aabb, HitRecord, Hittable, HittableList, Point, Ray, Sampler};
+
+/// Triangles sharing a common vertex buffer
+///
+/// Each triangle is three indices into the vertex attributes,
+/// so a vertex used by several triangles is stored only once.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    positions: Vec<Point>,
+    /// Per-vertex shading normals, interpolated across the faces
+    normals: Option<Vec<Point>>,
+    /// Per-vertex texture coordinates, interpolated across the faces
+    uvs: Option<Vec<(f64, f64)>>,
+    indices: Vec<[usize; 3]>,
+    material: Arc<dyn Material>,
+    /// Given to the hit records of every triangle
+    id: u32,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point>,
+        indices: Vec<[usize; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            indices.iter().flatten().all(|&i| i < positions.len()),
+            "Vertex index out of bounds"
+        );
+        Self {
+            positions,
+            normals: None,
+            uvs: None,
+            indices,
+            material,
+            id: 0,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Point>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "One normal per vertex");
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "One uv per vertex");
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Create a separate object for each triangle,
+    /// so the BVH can sort them individually
+    pub fn into_triangles(self) -> HittableList {
+        let mesh = Arc::new(self);
+        (0..mesh.len()).fold(HittableList::default(), |list, index| {
+            list.chain_add(Box::new(MeshTriangle {
+                mesh: mesh.clone(),
+                index,
+            }))
+        })
+    }
+
+    /// The values of a vertex attribute at the corners of a triangle
+    fn corners<T: Copy>(&self, attribute: &[T], index: usize) -> [T; 3] {
+        let [a, b, c] = self.indices[index];
+        [attribute[a], attribute[b], attribute[c]]
+    }
+
+    fn vertices(&self, index: usize) -> [Point; 3] {
+        self.corners(&self.positions, index)
+    }
+}
+
+/// A single triangle of a mesh
+#[derive(Debug)]
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mesh = &self.mesh;
+        let vertices = mesh.vertices(self.index);
+        let hit = triangle::intersect(r, &vertices, t_min, t_max)?;
+        let (w0, w1, w2) = (hit.b0(), hit.b1, hit.b2);
+
+        let (u, v) = match &mesh.uvs {
+            Some(uvs) => {
+                let [a, b, c] = mesh.corners(uvs, self.index);
+                (
+                    w0 * a.0 + w1 * b.0 + w2 * c.0,
+                    w0 * a.1 + w1 * b.1 + w2 * c.1,
+                )
+            }
+            None => (w1, w2),
+        };
+
+        let mut result = HitRecord {
+            t: hit.t,
+            position: r.point_at(hit.t).unwrap(),
+            material: mesh.material.clone(),
+            u,
+            v,
+            object_id: mesh.id,
+            front_face: false,        // by set_front_face
+            normal: Point::default(), // by set_front_face
+        };
+        result.set_front_face(r, &triangle::face_normal(&vertices));
+
+        // Smooth shading: the geometric normal decides which side was hit,
+        // the interpolated one is used for lighting
+        if let Some(normals) = &mesh.normals {
+            let [a, b, c] = mesh.corners(normals, self.index);
+            let shading_normal = (w0 * a + w1 * b + w2 * c).unit_vector();
+            result.normal = if result.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+
+        Some(result)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&self.mesh.vertices(self.index)))
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Point, _time: f64) -> f64 {
+        triangle::solid_angle_pdf(&self.mesh.vertices(self.index), origin, direction)
+    }
+
+    fn random(&self, origin: &Point, _time: f64, sampler: &mut Sampler) -> Option<Point> {
+        Some(triangle::random_towards(
+            &self.mesh.vertices(self.index),
+            origin,
+            sampler,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    /// A unit square in the z=0 plane, bent by its normals
+    fn quad() -> TriangleMesh {
+        let positions = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let normals = vec![
+            Point::new(-1.0, 0.0, 1.0).unit_vector(),
+            Point::new(1.0, 0.0, 1.0).unit_vector(),
+            Point::new(1.0, 0.0, 1.0).unit_vector(),
+            Point::new(-1.0, 0.0, 1.0).unit_vector(),
+        ];
+        let uvs = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        TriangleMesh::new(
+            positions,
+            vec![[0, 1, 2], [0, 2, 3]],
+            Arc::new(Lambertian::new(Color::white())),
+        )
+        .with_normals(normals)
+        .with_uvs(uvs)
+    }
+
+    #[test]
+    fn one_object_per_triangle() {
+        assert_eq!(quad().len(), 2);
+        assert_eq!(quad().into_triangles().into_objects().len(), 2);
+    }
+
+    #[test]
+    fn interpolated_normal() {
+        let triangles = quad().into_triangles();
+        let ray = Ray::new(Point::new(0.5, 0.25, 1.0), Point::new(0.0, 0.0, -1.0));
+        let hit = triangles.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!(hit.front_face);
+        assert!((hit.normal - Point::new(0.0, 0.0, 1.0)).near_zero());
+    }
+
+    #[test]
+    fn shading_normal_faces_the_ray() {
+        let triangles = quad().into_triangles();
+        let ray = Ray::new(Point::new(0.1, 0.5, -1.0), Point::new(0.0, 0.0, 1.0));
+        let hit = triangles.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!(!hit.front_face);
+        assert!(Point::dot(&hit.normal, ray.direction()) < 0.0);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use super::obj::ObjError;
+use crate::material::{self, Material};
+use crate::Color;
+
+/// Materials of an MTL library by name
+pub type MaterialLibrary = HashMap<String, Arc<dyn Material>>;
+
+/// The properties of a `newmtl` block this ray tracer understands
+#[derive(Debug, Clone, PartialEq)]
+struct MtlMaterial {
+    diffuse: Color,
+    specular: Color,
+    specular_exponent: f64,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::black(),
+            specular_exponent: 0.0,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Specular materials become metals,
+    /// the rest are diffuse
+    ///
+    /// The specular exponent is turned into roughness,
+    /// so a low `Ns` gives a fuzzy metal.
+    fn build(&self) -> Arc<dyn Material> {
+        if self.specular == Color::black() {
+            Arc::new(material::Lambertian::new(self.diffuse))
+        } else {
+            let fuzziness = (2.0 / (self.specular_exponent.max(0.0) + 2.0)).sqrt();
+            Arc::new(material::Metal::new(self.specular, fuzziness))
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<MaterialLibrary, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+    parse(&source).map_err(|(line, message)| ObjError::Parse {
+        path: path.to_owned(),
+        line,
+        message,
+    })
+}
+
+/// Parse the source of an MTL file
+///
+/// Errors are returned as line number and message
+fn parse(source: &str) -> Result<MaterialLibrary, (usize, String)> {
+    let mut materials = Vec::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+        let at_line = |message| (line_number, message);
+
+        match (keyword, &mut current) {
+            ("newmtl", _) => {
+                let name = args.join(" ");
+                if name.is_empty() {
+                    return Err(at_line(String::from("Missing material name")));
+                }
+                materials.extend(current.take());
+                current = Some((name, MtlMaterial::default()));
+            }
+            ("Kd" | "Ks" | "Ns", None) => {
+                return Err(at_line(format!("`{}` before `newmtl`", keyword)));
+            }
+            ("Kd", Some((_, material))) => {
+                material.diffuse = parse_color(&args).map_err(at_line)?;
+            }
+            ("Ks", Some((_, material))) => {
+                material.specular = parse_color(&args).map_err(at_line)?;
+            }
+            ("Ns", Some((_, material))) => {
+                material.specular_exponent = match args.as_slice() {
+                    [exponent] => parse_number(exponent).map_err(at_line)?,
+                    _ => return Err(at_line(String::from("Expected one number"))),
+                };
+            }
+            // Unsupported properties are skipped
+            _ => {}
+        }
+    }
+    materials.extend(current.take());
+
+    Ok(materials
+        .into_iter()
+        .map(|(name, material)| (name, material.build()))
+        .collect())
+}
+
+fn parse_color(args: &[&str]) -> Result<Color, String> {
+    match args {
+        [r, g, b] => Ok(Color::new(
+            parse_number(r)?,
+            parse_number(g)?,
+            parse_number(b)?,
+        )),
+        // A single value is used for every channel
+        [v] => {
+            let v = parse_number(v)?;
+            Ok(Color::new(v, v, v))
+        }
+        _ => Err(String::from("Expected an RGB color")),
+    }
+}
+
+fn parse_number(s: &str) -> Result<f64, String> {
+    s.parse()
+        .map_err(|_| format!("Expected a number, found `{}`", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffuse_and_metal() {
+        let source = "
+            # two materials
+            newmtl red
+            Kd 1 0 0
+
+            newmtl gold
+            Kd 0 0 0
+            Ks 1 0.8 0.3
+            Ns 6
+        ";
+        let library = parse(source).unwrap();
+        assert_eq!(library.len(), 2);
+        assert_eq!(
+            format!("{:?}", library["red"]),
+            format!("{:?}", material::Lambertian::new(Color::red()))
+        );
+        assert_eq!(
+            format!("{:?}", library["gold"]),
+            format!("{:?}", material::Metal::new(Color::new(1.0, 0.8, 0.3), 0.5))
+        );
+    }
+
+    #[test]
+    fn error_line_number() {
+        assert_eq!(
+            parse("newmtl a\nKd 1 x 0\n").unwrap_err(),
+            (2, String::from("Expected a number, found `x`"))
+        );
+        assert_eq!(parse("Kd 1 1 1").unwrap_err().0, 1);
+    }
+}
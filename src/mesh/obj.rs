@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::{mtl, TriangleMesh};
+use crate::material::Material;
+use crate::Point;
+
+/// A named part of an OBJ file
+#[derive(Debug)]
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: TriangleMesh,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Load the triangle meshes of a Wavefront OBJ file
+///
+/// Every group (`g` or `o`) gets a separate mesh for each material it uses.
+/// Faces with more than three vertices are split into a fan of triangles.
+/// Faces without a `usemtl` get the default material.
+pub fn load(path: &Path, default_material: Arc<dyn Material>) -> Result<Vec<ObjGroup>, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&source, path, default_material, |library| {
+        mtl::load(&directory.join(library))
+    })
+}
+
+/// Index of a vertex's position, texture coordinate and normal in the file
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+/// Collects the faces of a group which use the same material
+struct MeshBuilder {
+    name: String,
+    material: Arc<dyn Material>,
+    positions: Vec<Point>,
+    normals: Vec<Option<Point>>,
+    uvs: Vec<Option<(f64, f64)>>,
+    indices: Vec<[usize; 3]>,
+    /// The file indexes attributes separately,
+    /// the mesh needs one index for each unique combination
+    vertices: HashMap<VertexKey, usize>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, material: Arc<dyn Material>) -> Self {
+        Self {
+            name,
+            material,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            vertices: HashMap::new(),
+        }
+    }
+
+    fn vertex(&mut self, key: VertexKey, attributes: &Attributes) -> usize {
+        let Self {
+            positions,
+            normals,
+            uvs,
+            vertices,
+            ..
+        } = self;
+        *vertices.entry(key).or_insert_with(|| {
+            let (position, uv, normal) = key;
+            positions.push(attributes.positions[position]);
+            uvs.push(uv.map(|i| attributes.uvs[i]));
+            normals.push(normal.map(|i| attributes.normals[i]));
+            positions.len() - 1
+        })
+    }
+
+    /// Normals and uvs are only kept if every vertex has them
+    fn build(self) -> ObjGroup {
+        let mut mesh = TriangleMesh::new(self.positions, self.indices, self.material);
+        if let Some(normals) = self.normals.into_iter().collect() {
+            mesh = mesh.with_normals(normals);
+        }
+        if let Some(uvs) = self.uvs.into_iter().collect() {
+            mesh = mesh.with_uvs(uvs);
+        }
+        ObjGroup {
+            name: self.name,
+            mesh,
+        }
+    }
+}
+
+/// The vertex attributes declared so far
+#[derive(Default)]
+struct Attributes {
+    positions: Vec<Point>,
+    normals: Vec<Point>,
+    uvs: Vec<(f64, f64)>,
+}
+
+fn parse(
+    source: &str,
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    mut load_library: impl FnMut(&str) -> Result<mtl::MaterialLibrary, ObjError>,
+) -> Result<Vec<ObjGroup>, ObjError> {
+    let mut attributes = Attributes::default();
+    let mut materials = mtl::MaterialLibrary::new();
+
+    let mut builders: Vec<MeshBuilder> = Vec::new();
+    let mut builder_index: HashMap<(String, Option<String>), usize> = HashMap::new();
+    let mut group = String::from("default");
+    let mut material_name: Option<String> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let at_line = |message| ObjError::Parse {
+            path: path.to_owned(),
+            line: line_index + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_numbers::<3>(&args, 3).map_err(at_line)?;
+                attributes.positions.push(Point::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = parse_numbers::<3>(&args, 3).map_err(at_line)?;
+                attributes.normals.push(Point::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_numbers::<2>(&args, 1).map_err(at_line)?;
+                attributes.uvs.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(at_line(String::from(
+                        "A face needs at least three vertices",
+                    )));
+                }
+                let keys = args
+                    .iter()
+                    .map(|token| parse_vertex(token, &attributes))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(at_line)?;
+
+                let key = (group.clone(), material_name.clone());
+                let index = *builder_index.entry(key).or_insert_with(|| {
+                    let material = material_name
+                        .as_ref()
+                        .map_or_else(|| default_material.clone(), |m| materials[m].clone());
+                    builders.push(MeshBuilder::new(group.clone(), material));
+                    builders.len() - 1
+                });
+                let builder = &mut builders[index];
+
+                let vertices: Vec<usize> = keys
+                    .into_iter()
+                    .map(|key| builder.vertex(key, &attributes))
+                    .collect();
+                for i in 1..vertices.len() - 1 {
+                    builder
+                        .indices
+                        .push([vertices[0], vertices[i], vertices[i + 1]]);
+                }
+            }
+            "g" | "o" => {
+                group = if args.is_empty() {
+                    String::from("default")
+                } else {
+                    args.join(" ")
+                };
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                if !materials.contains_key(&name) {
+                    return Err(at_line(format!("Unknown material `{}`", name)));
+                }
+                material_name = Some(name);
+            }
+            "mtllib" => {
+                for library in args {
+                    materials.extend(load_library(library)?);
+                }
+            }
+            // Smoothing groups, lines, points, etc. are skipped
+            _ => {}
+        }
+    }
+
+    Ok(builders.into_iter().map(MeshBuilder::build).collect())
+}
+
+/// Parse at least `required` numbers, the missing ones are zero
+fn parse_numbers<const N: usize>(args: &[&str], required: usize) -> Result<[f64; N], String> {
+    if args.len() < required {
+        return Err(format!(
+            "Expected {} numbers, found {}",
+            required,
+            args.len()
+        ));
+    }
+    let mut numbers = [0.0; N];
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        *number = arg
+            .parse()
+            .map_err(|_| format!("Expected a number, found `{}`", arg))?;
+    }
+    Ok(numbers)
+}
+
+/// Parse a face vertex like `1`, `1/2`, `1//3` or `1/2/3`
+fn parse_vertex(token: &str, attributes: &Attributes) -> Result<VertexKey, String> {
+    let mut parts = token.split('/');
+    let position = resolve_index(parts.next().unwrap(), attributes.positions.len())?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, attributes.uvs.len())?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(resolve_index(index, attributes.normals.len())?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("Invalid face vertex `{}`", token));
+    }
+    Ok((position, uv, normal))
+}
+
+/// OBJ indices start from 1,
+/// negative indices count backwards from the last declared element
+fn resolve_index(token: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("Expected an index, found `{}`", token))?;
+    let resolved = match index {
+        0 => return Err(String::from("Indices start from 1")),
+        i if i > 0 => i - 1,
+        i => count as i64 + i,
+    };
+    if 0 <= resolved && resolved < count as i64 {
+        Ok(resolved as usize)
+    } else {
+        Err(format!("Index {} is out of range", index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color, Hittable, Ray};
+
+    fn parse_str(source: &str) -> Result<Vec<ObjGroup>, ObjError> {
+        let default_material = Arc::new(Lambertian::new(Color::white()));
+        parse(source, Path::new("test.obj"), default_material, |library| {
+            assert_eq!(library, "test.mtl");
+            let mut materials = mtl::MaterialLibrary::new();
+            materials.insert(String::from("red"), Arc::new(Lambertian::new(Color::red())));
+            Ok(materials)
+        })
+    }
+
+    #[test]
+    fn quad_is_triangulated() {
+        let groups = parse_str("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "default");
+        assert_eq!(groups[0].mesh.len(), 2);
+    }
+
+    #[test]
+    fn negative_indices() {
+        let groups = parse_str("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n").unwrap();
+        let triangles = groups.into_iter().next().unwrap().mesh.into_triangles();
+        let ray = Ray::new(Point::new(0.25, 0.25, 1.0), Point::new(0.0, 0.0, -1.0));
+        assert!(triangles.hit(&ray, 0.001, f64::INFINITY).is_some());
+    }
+
+    #[test]
+    fn normals_and_uvs() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 0 1
+            vn 0 0 1
+            f 1/1/1 2/2/1 3/3/1
+        ";
+        let groups = parse_str(source).unwrap();
+        let mesh = &groups[0].mesh;
+        assert!(mesh.normals.is_some());
+        assert!(mesh.uvs.is_some());
+        assert_eq!(mesh.positions.len(), 3);
+    }
+
+    #[test]
+    fn groups_and_materials() {
+        let source = "
+            mtllib test.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            g first
+            f 1 2 3
+            usemtl red
+            f 1 2 3
+            g second
+            f 1 2 3
+        ";
+        let groups = parse_str(source).unwrap();
+        let names: Vec<&str> = groups.iter().map(|g| g.name.as_ref()).collect();
+        assert_eq!(names, ["first", "first", "second"]);
+    }
+
+    #[test]
+    fn error_line_number() {
+        let error = parse_str("v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap_err();
+        assert_eq!(error.to_string(), "test.obj:3: Index 3 is out of range");
+
+        let error = parse_str("v 0 0 0\n\nv 1 zero 0\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "test.obj:3: Expected a number, found `zero`"
+        );
+
+        let error = parse_str("usemtl missing\n").unwrap_err();
+        assert_eq!(error.to_string(), "test.obj:1: Unknown material `missing`");
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Aabb, HitRecord, Hittable, Point, Ray, Sampler};
+
+/// Any object moving along a straight line
+///
+/// Time is measured in frames: the object is at its own place at time 0,
+/// and moved by `displacement` at time 1.
+/// It moves with constant speed in between, and rests before and after.
+#[derive(Debug)]
+pub struct Moving {
+    object: Box<dyn Hittable>,
+    displacement: Point,
+}
+
+impl Moving {
+    pub fn new(object: Box<dyn Hittable>, displacement: Point) -> Self {
+        Self {
+            object,
+            displacement,
+        }
+    }
+
+    fn offset(&self, time: f64) -> Point {
+        time.clamp(0.0, 1.0) * self.displacement
+    }
+}
+
+impl Hittable for Moving {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // Moving the ray backwards is the same as moving the object forwards
+        let offset = self.offset(r.time());
+        let moved = Ray::new(*r.origin() - offset, *r.direction()).with_time(r.time());
+        let mut hit = self.object.hit(&moved, t_min, t_max)?;
+        hit.position += offset;
+        Some(hit)
+    }
+
+    /// Covers the whole path, so it is valid for any shutter interval
+    fn bounding_box(&self) -> Option<Aabb> {
+        let start = self.object.bounding_box()?;
+        let end = start.translate(&self.displacement);
+        Some(Aabb::surrounding_box(&start, &end))
+    }
+
+    /// The directions from `origin` are those from the origin moved backwards
+    fn pdf_value(&self, origin: &Point, direction: &Point, time: f64) -> f64 {
+        let origin = *origin - self.offset(time);
+        self.object.pdf_value(&origin, direction, time)
+    }
+
+    fn random(&self, origin: &Point, time: f64, sampler: &mut Sampler) -> Option<Point> {
+        let origin = *origin - self.offset(time);
+        self.object.random(&origin, time, sampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color, Sphere};
+    use std::sync::Arc;
+
+    fn moving_sphere() -> Moving {
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let sphere = Sphere::new(Point::new(0.0, 0.0, -2.0), 0.5, material);
+        Moving::new(Box::new(sphere), Point::new(2.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn hit_where_it_is_at_the_time() {
+        let sphere = moving_sphere();
+        let towards_end = Ray::new(Point::new(2.0, 0.0, 0.0), Point::new(0.0, 0.0, -1.0));
+
+        assert!(sphere.hit(&towards_end, 0.001, f64::INFINITY).is_none());
+
+        let hit = sphere
+            .hit(&towards_end.with_time(1.0), 0.001, f64::INFINITY)
+            .unwrap();
+        assert_eq!(hit.position, Point::new(2.0, 0.0, -1.5));
+        assert_eq!(hit.normal, Point::new(0.0, 0.0, 1.0));
+
+        // Rests after the end of the motion
+        assert!(sphere
+            .hit(&towards_end.with_time(3.0), 0.001, f64::INFINITY)
+            .is_some());
+    }
+
+    #[test]
+    fn sampled_where_it_is_at_the_time() {
+        let sphere = moving_sphere();
+        let origin = Point::default();
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..100 {
+            let direction = sphere.random(&origin, 1.0, &mut sampler).unwrap();
+            let ray = Ray::new(origin, direction).with_time(1.0);
+            assert!(sphere.hit(&ray, 0.001, f64::INFINITY).is_some());
+            assert!(sphere.pdf_value(&origin, &direction, 1.0) > 0.0);
+            // Where the sphere was at the start
+            assert_eq!(sphere.pdf_value(&origin, &direction, 0.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn bounding_box_covers_the_path() {
+        let bbox = moving_sphere().bounding_box().unwrap();
+        assert_eq!(
+            bbox,
+            Aabb::new(Point::new(-0.5, -0.5, -2.5), Point::new(2.5, 0.5, -1.5))
+        );
+    }
+}
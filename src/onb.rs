@@ -0,0 +1,52 @@
+use super::Point;
+
+/// Orthonormal basis
+///
+/// Three perpendicular unit vectors, used to turn directions
+/// given relative to a surface or an axis into world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Onb {
+    u: Point,
+    v: Point,
+    w: Point,
+}
+
+impl Onb {
+    /// A basis whose `w` axis points in the given direction
+    pub fn from_w(w: &Point) -> Self {
+        let w = w.unit_vector();
+        // Any vector which is not parallel to w
+        let a = if w.x().abs() > 0.9 {
+            Point::new(0.0, 1.0, 0.0)
+        } else {
+            Point::new(1.0, 0.0, 0.0)
+        };
+        let v = Point::cross(&w, &a).unit_vector();
+        let u = Point::cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    /// Convert from the coordinates of this basis to world space
+    pub fn local(&self, a: &Point) -> Point {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perpendicular_unit_vectors() {
+        for w in &[Point::new(0.0, 0.0, 1.0), Point::new(1.0, 2.0, -3.0)] {
+            let onb = Onb::from_w(w);
+            assert!((onb.w - w.unit_vector()).near_zero());
+            for (a, b) in &[(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+                assert!(Point::dot(a, b).abs() < 1e-12);
+                assert!((a.len() - 1.0).abs() < 1e-12);
+            }
+            let z = onb.local(&Point::new(0.0, 0.0, 2.0));
+            assert!((z - 2.0 * w.unit_vector()).near_zero());
+        }
+    }
+}
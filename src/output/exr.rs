@@ -0,0 +1,188 @@
+use std::io::{self, Write};
+
+use crate::Film;
+
+/// `FLOAT` in the channel list
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+/// A picture in a multi-layer file
+///
+/// The channels are named `name.channel`, the first color components of the pixels are used
+#[derive(Debug, Copy, Clone)]
+pub struct Layer<'a> {
+    /// Empty for the beauty pass, its channels are just R, G and B
+    pub name: &'a str,
+    pub film: &'a Film,
+    pub channels: &'a [&'a str],
+}
+
+/// Write an uncompressed single part scanline OpenEXR file with 32-bit float channels
+pub fn write(film: &Film, out: &mut impl Write) -> io::Result<()> {
+    let layer = Layer {
+        name: "",
+        film,
+        channels: &["R", "G", "B"],
+    };
+    write_layers(&[layer], out)
+}
+
+/// Write the layers into one file, they have to be of the same size
+///
+/// Every scanline is a chunk of its own, found by the offset table after the header
+pub fn write_layers(layers: &[Layer], out: &mut impl Write) -> io::Result<()> {
+    let film = layers[0].film;
+    let (width, height) = (film.width() as i32, film.height() as i32);
+
+    // The channels have to be sorted by name, with the film and the color component
+    let mut all_channels: Vec<(String, &Film, usize)> = Vec::new();
+    for layer in layers {
+        for (i, channel) in layer.channels.iter().enumerate() {
+            let name = if layer.name.is_empty() {
+                channel.to_string()
+            } else {
+                format!("{}.{}", layer.name, channel)
+            };
+            all_channels.push((name, layer.film, i));
+        }
+    }
+    all_channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut channels = Vec::new();
+    for (name, _, _) in &all_channels {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        // Not perceptually linear, then reserved bytes
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        // No subsampling
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+
+    // No compression
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window = [0, 0, width - 1, height - 1];
+    let window: Vec<u8> = window.iter().flat_map(|v| v.to_le_bytes()).collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // y, size, then the floats of each channel
+    let chunk_size = 8 + 4 * all_channels.len() * film.width();
+    let first_chunk = header.len() + 8 * film.height();
+    out.write_all(&header)?;
+    for y in 0..film.height() {
+        let offset = (first_chunk + y * chunk_size) as u64;
+        out.write_all(&offset.to_le_bytes())?;
+    }
+
+    for y in 0..film.height() {
+        out.write_all(&(y as i32).to_le_bytes())?;
+        out.write_all(&((chunk_size - 8) as i32).to_le_bytes())?;
+        for (_, film, component) in &all_channels {
+            for x in 0..film.width() {
+                out.write_all(&(film.pixel(x, y)[*component] as f32).to_le_bytes())?;
+            }
+        }
+    }
+    out.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn read_u64(bytes: &[u8], at: usize) -> usize {
+        let mut le = [0; 8];
+        le.copy_from_slice(&bytes[at..at + 8]);
+        u64::from_le_bytes(le) as usize
+    }
+
+    fn read_f32(bytes: &[u8], at: usize) -> f32 {
+        f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn chunks_are_found_by_the_offsets() {
+        let film = Film::from_fn(3, 2, |x, y| Color::new(x as f64, y as f64, 10.0));
+        let mut out = Vec::new();
+        write(&film, &mut out).unwrap();
+
+        assert_eq!(&out[..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // The offset table is between the header and the chunks
+        let chunk_size = 8 + 4 * 3 * 3;
+        let table = out.len() - 2 * chunk_size - 2 * 8;
+        let second = read_u64(&out, table + 8);
+        assert_eq!(second, out.len() - chunk_size);
+
+        // Second row: B, B, B, G, G, G, R, R, R
+        assert_eq!(&out[second..second + 4], &1i32.to_le_bytes());
+        let data = second + 8;
+        assert_eq!(read_f32(&out, data), 10.0);
+        assert_eq!(read_f32(&out, data + 4 * 3), 1.0);
+        assert_eq!(read_f32(&out, data + 4 * 8), 2.0);
+    }
+
+    #[test]
+    fn layer_channels_are_sorted() {
+        let beauty = Film::from_fn(2, 1, |_, _| Color::new(1.0, 2.0, 3.0));
+        let depth = Film::from_fn(2, 1, |x, _| Color::new(x as f64 + 5.0, 0.0, 0.0));
+        let layers = [
+            Layer {
+                name: "",
+                film: &beauty,
+                channels: &["R", "G", "B"],
+            },
+            Layer {
+                name: "depth",
+                film: &depth,
+                channels: &["Z"],
+            },
+        ];
+        let mut out = Vec::new();
+        write_layers(&layers, &mut out).unwrap();
+
+        let header = String::from_utf8_lossy(&out);
+        let position = |name: &str| header.find(&format!("{}\0", name)).unwrap();
+        assert!(position("B") < position("G") && position("G") < position("R"));
+        assert!(position("R") < position("depth.Z"));
+
+        // B, B, G, G, R, R, depth.Z, depth.Z
+        let chunk = out.len() - 8 * 4;
+        assert_eq!(read_f32(&out, chunk), 3.0);
+        assert_eq!(read_f32(&out, chunk + 4 * 4), 1.0);
+        assert_eq!(read_f32(&out, chunk + 4 * 7), 6.0);
+    }
+}
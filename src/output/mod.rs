@@ -0,0 +1,284 @@
+mod exr;
+mod pfm;
+mod tone_map;
+
+use image::{codecs::hdr::HdrEncoder, ImageBuffer, Rgb, RgbImage};
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{aov::Aov, Film};
+
+pub use tone_map::{ToneMapping, ToneOperator};
+
+/// How the picture is stored, chosen by the extension of the output file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// 8 bits per channel in sRGB, tone mapped
+    Ldr,
+    /// 32-bit float OpenEXR, linear
+    Exr,
+    /// Radiance RGBE, linear
+    Hdr,
+    /// Portable float map, linear
+    Pfm,
+}
+
+impl Format {
+    /// The accepted extensions of the output file
+    pub const EXTENSIONS: [&'static str; 11] = [
+        "png", "jpg", "jpeg", "bmp", "tga", "tif", "tiff", "ppm", "exr", "hdr", "pfm",
+    ];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_ref() {
+            "exr" => Some(Format::Exr),
+            "hdr" => Some(Format::Hdr),
+            "pfm" => Some(Format::Pfm),
+            other if Self::EXTENSIONS.contains(&other) => Some(Format::Ldr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    /// The extension of the file doesn't name a known format
+    UnknownFormat,
+    Io(io::Error),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::UnknownFormat => write!(
+                f,
+                "Unknown output format, expected one of the extensions: {}",
+                Format::EXTENSIONS.join(", ")
+            ),
+            OutputError::Io(error) => write!(f, "{}", error),
+            OutputError::Image(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OutputError::UnknownFormat => None,
+            OutputError::Io(error) => Some(error),
+            OutputError::Image(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(error: io::Error) -> Self {
+        OutputError::Io(error)
+    }
+}
+
+impl From<image::ImageError> for OutputError {
+    fn from(error: image::ImageError) -> Self {
+        OutputError::Image(error)
+    }
+}
+
+/// Write the picture in the format of the file's extension
+///
+/// The float formats keep the linear colors,
+/// only the 8-bit formats are tone mapped and get the sRGB transfer function
+pub fn save(film: &Film, path: &Path, tone_mapping: &ToneMapping) -> Result<(), OutputError> {
+    let format = Format::from_path(path).ok_or(OutputError::UnknownFormat)?;
+    match format {
+        Format::Ldr => {
+            let img = ImageBuffer::from_fn(film.width() as _, film.height() as _, |x, y| {
+                let color = tone_mapping.apply(film.pixel(x as _, y as _));
+                let (r, g, b) = color.rgb_bytes(1);
+                Rgb([r, g, b])
+            });
+            img.save(path)?;
+        }
+        Format::Exr => exr::write(film, &mut BufWriter::new(File::create(path)?))?,
+        Format::Hdr => save_hdr(film, path)?,
+        Format::Pfm => pfm::write(film, &mut BufWriter::new(File::create(path)?))?,
+    }
+    Ok(())
+}
+
+fn save_hdr(film: &Film, path: &Path) -> Result<(), OutputError> {
+    let pixels: Vec<_> = film
+        .rows()
+        .flatten()
+        .map(|c| Rgb([c[0] as f32, c[1] as f32, c[2] as f32]))
+        .collect();
+    let file = BufWriter::new(File::create(path)?);
+    HdrEncoder::new(file).encode(&pixels, film.width(), film.height())?;
+    Ok(())
+}
+
+/// Where an AOV of the picture at `path` is written by `save_aovs`
+///
+/// `render.png` gets `render.depth.exr`, a `.pfm` picture gets `.pfm` AOVs.
+/// Never `.hdr`, RGBE can't store the negative normals and positions
+pub fn aov_path(path: &Path, aov: Aov) -> PathBuf {
+    let extension = match Format::from_path(path) {
+        Some(Format::Pfm) => path.extension().unwrap().to_str().unwrap(),
+        _ => "exr",
+    };
+    path.with_extension(format!("{}.{}", aov, extension))
+}
+
+/// Where the picture before the denoiser is written, `render.png` gets `render.raw.png`
+pub fn raw_path(path: &Path) -> PathBuf {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    path.with_extension(format!("raw.{}", extension))
+}
+
+/// Where the samples per pixel are drawn, `render.exr` gets `render.samples.png`
+pub fn heatmap_path(path: &Path) -> PathBuf {
+    path.with_extension("samples.png")
+}
+
+/// The samples of the pixels from dark blue for none, through green, to red for `max`
+///
+/// Row by row from the top
+pub fn heatmap(samples: &[usize], width: usize, height: usize, max: usize) -> RgbImage {
+    // Evenly spaced stops of the ramp
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.3],
+        [0.0, 0.5, 1.0],
+        [0.2, 0.8, 0.2],
+        [1.0, 0.9, 0.0],
+        [0.9, 0.0, 0.0],
+    ];
+    ImageBuffer::from_fn(width as _, height as _, |x, y| {
+        let t = samples[y as usize * width + x as usize] as f64 / max.max(1) as f64;
+        let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+        let i = (position as usize).min(STOPS.len() - 2);
+        let f = position - i as f64;
+        let channel = |c: usize| {
+            let value = STOPS[i][c] + f * (STOPS[i + 1][c] - STOPS[i][c]);
+            (255.0 * value).round() as u8
+        };
+        Rgb([channel(0), channel(1), channel(2)])
+    })
+}
+
+/// Write each AOV into a file of its own next to the picture, see `aov_path`
+///
+/// The EXR files have the channels of the AOV, the PFM files repeat single values in RGB
+pub fn save_aovs(aovs: &[(Aov, Film)], path: &Path) -> Result<(), OutputError> {
+    for (aov, film) in aovs {
+        let path = aov_path(path, *aov);
+        match Format::from_path(&path) {
+            Some(Format::Pfm) => pfm::write(film, &mut BufWriter::new(File::create(&path)?))?,
+            _ => {
+                let layer = exr::Layer {
+                    name: "",
+                    film,
+                    channels: aov.channels(),
+                };
+                exr::write_layers(&[layer], &mut BufWriter::new(File::create(&path)?))?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the picture and the AOVs into one EXR file, the AOVs are layers named after them
+pub fn save_layers(film: &Film, aovs: &[(Aov, Film)], path: &Path) -> Result<(), OutputError> {
+    let names: Vec<_> = aovs.iter().map(|(aov, _)| aov.to_string()).collect();
+    let mut layers = vec![exr::Layer {
+        name: "",
+        film,
+        channels: &["R", "G", "B"],
+    }];
+    for ((aov, film), name) in aovs.iter().zip(&names) {
+        layers.push(exr::Layer {
+            name,
+            film,
+            channels: aov.channels(),
+        });
+    }
+    exr::write_layers(&layers, &mut BufWriter::new(File::create(path)?))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn format_from_extension() {
+        let format = |path| Format::from_path(Path::new(path));
+        assert_eq!(format("render.png"), Some(Format::Ldr));
+        assert_eq!(format("render.JPG"), Some(Format::Ldr));
+        assert_eq!(format("out/render.exr"), Some(Format::Exr));
+        assert_eq!(format("render.hdr"), Some(Format::Hdr));
+        assert_eq!(format("render.pfm"), Some(Format::Pfm));
+        assert_eq!(format("render.txt"), None);
+        assert_eq!(format("render"), None);
+    }
+
+    #[test]
+    fn aov_files_next_to_the_picture() {
+        let path = |output, aov| aov_path(Path::new(output), aov);
+        assert_eq!(
+            path("out/render.png", Aov::Depth),
+            Path::new("out/render.depth.exr")
+        );
+        assert_eq!(
+            path("render.pfm", Aov::Normal),
+            Path::new("render.normal.pfm")
+        );
+        assert_eq!(path("render.exr", Aov::Id), Path::new("render.id.exr"));
+        assert_eq!(
+            path("render.hdr", Aov::Position),
+            Path::new("render.position.exr")
+        );
+        assert_eq!(
+            raw_path(Path::new("render.png")),
+            Path::new("render.raw.png")
+        );
+        assert_eq!(
+            heatmap_path(Path::new("render.exr")),
+            Path::new("render.samples.png")
+        );
+    }
+
+    #[test]
+    fn heatmap_ramp() {
+        let heatmap = heatmap(&[0, 50, 100, 400], 2, 2, 100);
+        assert_eq!(heatmap.get_pixel(0, 0), &Rgb([0, 0, 77]));
+        assert_eq!(heatmap.get_pixel(1, 0), &Rgb([51, 204, 51]));
+        assert_eq!(heatmap.get_pixel(0, 1), &Rgb([230, 0, 0]));
+        // Beyond the most is clamped
+        assert_eq!(heatmap.get_pixel(1, 1), heatmap.get_pixel(0, 1));
+    }
+
+    #[test]
+    fn negative_normals_are_kept() {
+        let normal = Film::from_fn(1, 1, |_, _| Color::new(0.6, -0.8, 0.0));
+        let directory = std::env::temp_dir();
+        let output = directory.join(format!("aovs-{}.hdr", std::process::id()));
+        save_aovs(&[(Aov::Normal, normal)], &output).unwrap();
+
+        let path = aov_path(&output, Aov::Normal);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // The only pixel ends the file: X, Y, Z
+        let y = &bytes[bytes.len() - 8..bytes.len() - 4];
+        assert_eq!(f32::from_le_bytes([y[0], y[1], y[2], y[3]]), -0.8);
+    }
+}
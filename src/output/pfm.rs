@@ -0,0 +1,40 @@
+use std::io::{self, Write};
+
+use crate::Film;
+
+/// Write a color portable float map
+///
+/// The rows are stored from the bottom, a negative scale means little-endian floats
+pub fn write(film: &Film, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "PF\n{} {}\n-1.0\n", film.width(), film.height())?;
+    let rows: Vec<_> = film.rows().collect();
+    for row in rows.into_iter().rev() {
+        for color in row {
+            for i in 0..3 {
+                out.write_all(&(color[i] as f32).to_le_bytes())?;
+            }
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn bottom_row_first() {
+        let film = Film::from_fn(1, 2, |_, y| Color::new(y as f64, 0.5, 2.0));
+        let mut out = Vec::new();
+        write(&film, &mut out).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], header);
+        let floats: Vec<f32> = out[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(floats, vec![1.0, 0.5, 2.0, 0.0, 0.5, 2.0]);
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+use crate::Color;
+
+/// How the unbounded light is squeezed into the displayable range
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToneOperator {
+    /// Everything brighter than white is white
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` on the luminance, never reaches white
+    Reinhard,
+    /// Reinhard which reaches white at the white point
+    ExtendedReinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2
+    Hable,
+}
+
+impl ToneOperator {
+    /// The accepted names of the tone operators
+    pub const VARIANTS: [&'static str; 5] =
+        ["clamp", "reinhard", "extended-reinhard", "aces", "hable"];
+
+    /// The brightness which becomes white, if the operator has one
+    fn default_white_point(&self) -> f64 {
+        match self {
+            ToneOperator::Hable => 11.2,
+            _ => 4.0,
+        }
+    }
+}
+
+impl FromStr for ToneOperator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "clamp" => Ok(ToneOperator::Clamp),
+            "reinhard" => Ok(ToneOperator::Reinhard),
+            "extended-reinhard" => Ok(ToneOperator::ExtendedReinhard),
+            "aces" => Ok(ToneOperator::Aces),
+            "hable" => Ok(ToneOperator::Hable),
+            _ => Err(format!("Unknown tone operator: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ToneOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ToneOperator::Clamp => "clamp",
+            ToneOperator::Reinhard => "reinhard",
+            ToneOperator::ExtendedReinhard => "extended-reinhard",
+            ToneOperator::Aces => "aces",
+            ToneOperator::Hable => "hable",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Turns the linear light of the render into linear display colors between 0 and 1
+///
+/// Only the 8-bit outputs are tone mapped, the float formats keep the light as it is
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ToneMapping {
+    /// In stops, every stop doubles the light
+    pub exposure: f64,
+    pub operator: ToneOperator,
+    /// The brightness which becomes white for the extended Reinhard and Hable operators
+    pub white_point: Option<f64>,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: Color) -> Color {
+        let color = 2f64.powf(self.exposure) * color;
+        let white = self
+            .white_point
+            .unwrap_or_else(|| self.operator.default_white_point());
+
+        let mapped = match self.operator {
+            ToneOperator::Clamp => color,
+            ToneOperator::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneOperator::ExtendedReinhard => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneOperator::Aces => per_channel(color, |x| {
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+            ToneOperator::Hable => per_channel(color, |x| hable(x) / hable(white)),
+        };
+        per_channel(mapped, |x| x.clamp(0.0, 1.0))
+    }
+}
+
+/// Maps the luminance and keeps the hue
+fn scale_luminance(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    let luminance = color.luminance();
+    if luminance <= 0.0 {
+        return Color::black();
+    }
+    (f(luminance) / luminance) * color
+}
+
+fn per_channel(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(color[0]), f(color[1]), f(color[2]))
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f64) -> Color {
+        Color::new(value, value, value)
+    }
+
+    #[test]
+    fn exposure_doubles_per_stop() {
+        let tone_mapping = ToneMapping {
+            exposure: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(tone_mapping.apply(gray(0.1)), gray(0.4));
+        assert_eq!(tone_mapping.apply(gray(0.5)), Color::white());
+    }
+
+    #[test]
+    fn operators_keep_highlights_apart() {
+        for name in ToneOperator::VARIANTS.iter() {
+            let operator: ToneOperator = name.parse().unwrap();
+            assert_eq!(&operator.to_string(), name);
+            if operator == ToneOperator::Clamp {
+                continue;
+            }
+            let tone_mapping = ToneMapping {
+                operator,
+                ..Default::default()
+            };
+            let values: Vec<f64> = [0.0, 0.1, 1.0, 2.0, 3.0]
+                .iter()
+                .map(|&v| tone_mapping.apply(gray(v))[0])
+                .collect();
+            assert!(values[0].abs() < 1e-2, "{} of black is {}", name, values[0]);
+            assert!(
+                values.windows(2).all(|w| w[0] < w[1] && w[1] <= 1.0),
+                "{} isn't increasing: {:?}",
+                name,
+                values
+            );
+        }
+    }
+
+    #[test]
+    fn white_point_becomes_white() {
+        for &operator in &[ToneOperator::ExtendedReinhard, ToneOperator::Hable] {
+            let tone_mapping = ToneMapping {
+                operator,
+                white_point: Some(6.0),
+                ..Default::default()
+            };
+            let white = tone_mapping.apply(gray(6.0));
+            assert!((white[0] - 1.0).abs() < 1e-12);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
+use crate::Sampler;
+use serde::Deserialize;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
 pub struct Point(f64, f64, f64);
 
 impl Point {
@@ -18,27 +21,24 @@ impl Point {
         self.2
     }
 
-    pub fn random_range(min: f64, max: f64) -> Self {
-        use rand::random;
-        let r = || min + (max - min) * random::<f64>();
+    pub fn random_range(min: f64, max: f64, sampler: &mut Sampler) -> Self {
+        let mut r = || sampler.range(min, max);
         Self(r(), r(), r())
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere(sampler: &mut Sampler) -> Self {
         loop {
-            let p = Self::random_range(-1.0, 1.0);
+            let p = Self::random_range(-1.0, 1.0, sampler);
             if p.len_squared() < 1.0 {
                 return p;
             }
         }
     }
 
-    pub fn random_unit_vec() -> Self {
-        use rand::random;
+    pub fn random_unit_vec(sampler: &mut Sampler) -> Self {
         use std::f64::consts::PI;
-        let r = |min, max| min + (max - min) * random::<f64>();
-        let a: f64 = r(0.0, 2.0 * PI);
-        let z = r(-1.0, 1.0);
+        let a: f64 = sampler.range(0.0, 2.0 * PI);
+        let z = sampler.range(-1.0, 1.0);
         let r = (1.0 - z * z).sqrt();
         Self(r * a.cos(), r * a.sin(), z)
     }
@@ -69,15 +69,45 @@ impl Point {
 
     /// Reflect self to a normal vector
     pub fn reflect(&self, normal: &Self) -> Self {
-        let b_len = Self::dot(&self, &normal);
+        let b_len = Self::dot(self, normal);
         let b = b_len * *normal;
         *self - 2.0 * b
     }
 
+    /// Refract self (a unit vector) through a surface with the given normal
+    ///
+    /// The ratio is the refractive index of the medium the vector comes from
+    /// over the refractive index of the medium it enters
+    pub fn refract(&self, normal: &Self, ratio: f64) -> Self {
+        let cos_theta = Self::dot(&-*self, normal).min(1.0);
+        let perpendicular = ratio * (*self + cos_theta * *normal);
+        let parallel = -(1.0 - perpendicular.len_squared()).abs().sqrt() * *normal;
+        perpendicular + parallel
+    }
+
     /// Return true if the vector is close to zero in all dimensions.
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
-        return self.0.abs() < s && self.1.abs() < s && self.2.abs() < s;
+        self.0.abs() < s && self.1.abs() < s && self.2.abs() < s
+    }
+}
+
+/// Parse a point written as `x,y,z`
+impl std::str::FromStr for Point {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coordinates = s
+            .split(',')
+            .map(|c| c.trim().parse::<f64>().map_err(|err| err.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match coordinates.as_slice() {
+            [x, y, z] => Ok(Self::new(*x, *y, *z)),
+            _ => Err(format!(
+                "Expected three coordinates like `1,2,3`, found {}",
+                coordinates.len()
+            )),
+        }
     }
 }
 
@@ -177,4 +207,24 @@ mod tests {
         let expected_result = Point::new(-1.0, 0.0, 1.0);
         assert_eq!(v1, expected_result);
     }
+
+    #[test]
+    fn refract_follows_snell() {
+        let normal = Point::new(0.0, 1.0, 0.0);
+        let incoming = Point::new(1.0, -1.0, 0.0).unit_vector();
+        let refracted = incoming.refract(&normal, 1.0 / 1.5);
+        let sin_in = incoming.x();
+        let sin_out = refracted.x() / refracted.len();
+        assert!((sin_in - 1.5 * sin_out).abs() < 1e-12);
+
+        let straight = Point::new(0.0, -1.0, 0.0);
+        assert_eq!(straight.refract(&normal, 1.0 / 1.5), straight);
+    }
+
+    #[test]
+    fn parse_point() {
+        assert_eq!("1,-2.5, 3".parse(), Ok(Point::new(1.0, -2.5, 3.0)));
+        assert!("1,2".parse::<Point>().is_err());
+        assert!("1,2,z".parse::<Point>().is_err());
+    }
 }
@@ -1,10 +1,27 @@
-use super::{Color, Hittable, HittableList, Point};
+use super::{Color, HitRecord, Point, Sampler, World};
+use std::cell::Cell;
+
+thread_local! {
+    /// Rays sent into the scene by this thread, the shadow rays included
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The number of rays traced by the current thread so far
+pub fn rays_traced() -> u64 {
+    RAYS_TRACED.with(Cell::get)
+}
+
+fn count_ray() {
+    RAYS_TRACED.with(|rays| rays.set(rays.get() + 1));
+}
 
 /// Create a ray that goes from origin to infinity in a given direction
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Ray {
     origin: Point,
     direction: Point,
+    /// When the ray was sent, moving objects are hit where they are at this time
+    time: f64,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -14,7 +31,16 @@ pub enum RayError {
 
 impl Ray {
     pub fn new(origin: Point, direction: Point) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
     }
 
     pub fn origin(&self) -> &Point {
@@ -25,21 +51,25 @@ impl Ray {
         &self.direction
     }
 
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
     /// This method returns the current location of the ray if it wouldn't hit a sphere
     /// (maybe it really didn't hit it)
     ///
-    /// Please not the parameter is only named time because
-    /// it behaves like time in physic's motion
+    /// Please note the parameter behaves like time in physic's motion,
+    /// but it is not the time of the ray (that is when the ray was sent)
     ///
     /// When writing a ray tracer every point
     /// between origin and infinity
     /// should be tested for collision against all available objects
     /// to find out if it hit any (and hence the ray is blocked)
-    pub fn point_at(&self, time: f64) -> Result<Point, RayError> {
+    pub fn point_at(&self, t: f64) -> Result<Point, RayError> {
         // Only real position is supported
         // Ray should never go backwards
-        if time >= 0.0 {
-            Ok(self.origin + time * self.direction)
+        if t >= 0.0 {
+            Ok(self.origin + t * self.direction)
         } else {
             Err(RayError::InvalidDirection)
         }
@@ -51,16 +81,35 @@ impl Ray {
     /// - hits an object
     ///
     /// It will take some of it's color
-    /// and a weaker new ray will continue on approximately the hit's normal vector
+    /// and a weaker new ray will continue on approximately the hit's normal vector.
+    /// A shadow ray is also sent towards a random point of the lights (next event estimation)
     ///
     /// - gets stuck between two objects
     ///
     /// It will slowly fade away
     ///
+    /// - hits a light
+    ///
+    /// It will add the light's emission to the color
+    ///
     /// - does not hit anything
     ///
-    /// It will simulate the color of the sky
-    pub fn color(&self, world: &HittableList, allowed_collisions: usize) -> Color {
+    /// It will take the light of the environment, black if the sky is turned off
+    ///
+    /// All the random choices are made by the sampler
+    pub fn color(&self, world: &World, allowed_collisions: usize, sampler: &mut Sampler) -> Color {
+        self.trace(world, allowed_collisions, None, sampler)
+    }
+
+    /// `scatter_pdf` is the density the previous bounce chose this ray with,
+    /// `None` for camera rays and mirror-like bounces, which the light sampling can't produce
+    fn trace(
+        &self,
+        world: &World,
+        allowed_collisions: usize,
+        scatter_pdf: Option<f64>,
+        sampler: &mut Sampler,
+    ) -> Color {
         // The function is called recursively
         // The start of each iteration = hit point + 0.0000*t
         // The direction of the new ray is the normal vector of the object + some random (anti aliased)
@@ -68,26 +117,80 @@ impl Ray {
             // Stuck in a mirror room
             // The ray will fade away here
             return Color::black();
-        } else if let Some(hit) = world.hit(&self, 0.001, f64::INFINITY) {
+        }
+        count_ray();
+        if let Some(hit) = world.objects.hit(self, 0.001, f64::INFINITY) {
             // Hit an object
-            if let Some(mat) = hit.material.scatter(&self, &hit) {
-                mat.attenuation * mat.scattered.color(&world, allowed_collisions - 1)
-            } else {
-                Color::black()
-            }
+            let emitted = self.weigh_light(world, hit.material.emitted(&hit), scatter_pdf);
 
-        // return 0.5 * new_ray.color(&world, allowed_collisions - 1);
+            let mat = match hit.material.scatter(self, &hit, sampler) {
+                Some(mat) => mat,
+                None => return emitted,
+            };
+            let direct = match mat.pdf {
+                Some(_) => self.sample_lights(world, &hit, sampler) * mat.attenuation,
+                None => Color::black(),
+            };
+            let indirect = mat.attenuation
+                * mat
+                    .scattered
+                    .trace(world, allowed_collisions - 1, mat.pdf, sampler);
+            emitted + direct + indirect
         } else {
             // Reached Infinity
-            // Let's give the sky a nice gradient color
-            // based on the y coordinate
-            // t=0 => start_value
-            // t=1 => end_value
-            let unit_vec = self.direction.unit_vector();
-            let t = 0.5 * (unit_vec.y() + 1.0);
-            let start_value = Color::white();
-            let end_value = Color::new(0.5, 0.7, 1.0);
-            return (1.0 - t) * start_value + t * end_value;
+            let background = world.environment.color(&self.direction);
+            self.weigh_light(world, background, scatter_pdf)
+        }
+    }
+
+    /// Light found by this ray, weighted against the light sampling of the previous bounce
+    /// which could have found it too
+    fn weigh_light(&self, world: &World, light: Color, scatter_pdf: Option<f64>) -> Color {
+        match scatter_pdf {
+            Some(scatter_pdf) if light != Color::black() => {
+                let light_pdf = world.light_pdf(&self.origin, &self.direction, self.time);
+                light * power_heuristic(scatter_pdf, light_pdf)
+            }
+            _ => light,
+        }
+    }
+
+    /// Send a shadow ray from the hit towards a random point of the lights or the environment
+    ///
+    /// The result is weighted against finding the same light by scattering,
+    /// and still has to be multiplied by the attenuation
+    fn sample_lights(&self, world: &World, hit: &HitRecord, sampler: &mut Sampler) -> Color {
+        if !world.has_lights() {
+            return Color::black();
+        }
+        let direction = match world.random_light(&hit.position, self.time, sampler) {
+            Some(direction) => direction,
+            None => return Color::black(),
+        };
+        let light_pdf = world.light_pdf(&hit.position, &direction, self.time);
+        let scatter_pdf = hit.material.scattering_pdf(self, hit, &direction);
+        if light_pdf <= 0.0 || scatter_pdf <= 0.0 {
+            return Color::black();
         }
+
+        // Whatever the shadow ray hits first gives the light, so occluders cast shadows
+        let shadow_ray = Ray::new(hit.position, direction).with_time(self.time);
+        count_ray();
+        let light = match world.objects.hit(&shadow_ray, 0.001, f64::INFINITY) {
+            Some(light) => light.material.emitted(&light),
+            None => world.environment.color(&direction),
+        };
+        let weight = power_heuristic(light_pdf, scatter_pdf);
+        light * (weight * scatter_pdf / light_pdf)
+    }
+}
+
+/// Multiple importance sampling weight of a sample from the first strategy
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
     }
 }
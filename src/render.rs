@@ -0,0 +1,611 @@
+use super::{aov::FirstHit, interrupt, ray, Camera, Color, Sampler, World};
+
+use rayon::prelude::*;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Side of the square tiles in pixels
+pub const TILE_SIZE: usize = 32;
+
+/// The adaptive sampling compares the error of darker pixels to this luminance
+const MIN_LUMINANCE: f64 = 0.01;
+
+/// The most samples a round of `Renderer::render` adds, so the checkpoints between them come often
+const MAX_ROUND_SAMPLES: usize = 64;
+
+/// A rectangle of the picture which is rendered by one thread
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    /// The top left pixel, the rows go from the top
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    /// Cover the picture row by row from the top, the tiles at the right and the bottom are smaller
+    pub fn split(width: usize, height: usize, size: usize) -> Vec<Self> {
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(size) {
+            for x in (0..width).step_by(size) {
+                tiles.push(Self {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                });
+            }
+        }
+        tiles
+    }
+}
+
+/// The samples of a pixel added up
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Pixel {
+    pub color: Color,
+    /// Only found if the renderer was asked to
+    pub first_hit: FirstHit,
+    /// Sum of the squared luminances, for the variance
+    pub luminance_squares: f64,
+    pub samples: usize,
+}
+
+impl Pixel {
+    /// Add the samples of the other pixel, which were taken after these
+    pub fn add(&mut self, other: &Pixel) {
+        self.color += other.color;
+        self.luminance_squares += other.luminance_squares;
+        self.first_hit = if self.samples == 0 {
+            other.first_hit
+        } else {
+            self.first_hit.accumulate(other.first_hit)
+        };
+        self.samples += other.samples;
+    }
+
+    /// The color of the pixel, black if it has no samples yet
+    pub fn average(&self) -> Color {
+        if self.samples == 0 {
+            Color::black()
+        } else {
+            self.color * (1.0 / self.samples as f64)
+        }
+    }
+
+    /// Standard error of the average luminance, infinite below two samples
+    pub fn standard_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.samples as f64;
+        let mean = self.color.luminance() / n;
+        let variance = (self.luminance_squares / n - mean * mean).max(0.0) * n / (n - 1.0);
+        (variance / n).sqrt()
+    }
+}
+
+/// Stops sampling the pixels which are not noisy any more
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Adaptive {
+    /// The allowed standard error relative to the luminance of the pixel
+    pub threshold: f64,
+    /// Every pixel gets this many samples before its error is trusted
+    pub min_samples: usize,
+}
+
+impl Adaptive {
+    pub fn converged(&self, pixel: &Pixel) -> bool {
+        let luminance = pixel.average().luminance().max(MIN_LUMINANCE);
+        pixel.samples >= self.min_samples && pixel.standard_error() <= self.threshold * luminance
+    }
+}
+
+/// When a progressive render writes the picture, and when it stops before all the samples
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Progressive {
+    /// Seconds between the snapshots
+    pub snapshot_seconds: Option<f64>,
+    /// Passes between the snapshots
+    pub snapshot_passes: Option<usize>,
+    /// Seconds after which the render stops
+    pub time_limit: Option<f64>,
+}
+
+/// Sends the camera rays of the pixels into the world
+#[derive(Debug, Copy, Clone)]
+pub struct Renderer<'a> {
+    pub camera: &'a Camera,
+    pub world: &'a World,
+    pub width: usize,
+    pub height: usize,
+    pub max_ray_depth: usize,
+    /// Whether the AOVs or the denoiser need the first hits
+    pub find_first_hits: bool,
+    /// `None` gives every pixel all the samples
+    pub adaptive: Option<Adaptive>,
+    /// With the pixel and the index of the sample it decides the random numbers of a sample
+    pub seed: u64,
+}
+
+impl Renderer<'_> {
+    /// The pixels of the tile with their new samples, row by row
+    ///
+    /// The pixels of the picture are given with the samples they already have,
+    /// each gets as many more as it needs to have `samples`, unless it has converged.
+    /// The samples are added one after the other, so the sums don't depend on
+    /// how the render was split into passes
+    pub fn render_tile(&self, tile: &Tile, pixels: &[Pixel], samples: usize) -> Vec<Pixel> {
+        let rounds = self.rounds(samples);
+        let mut rendered = Vec::with_capacity(tile.width * tile.height);
+        for y in tile.y..tile.y + tile.height {
+            for x in tile.x..tile.x + tile.width {
+                let index = y * self.width + x;
+                let mut pixel = pixels[index];
+                let taken = pixel.samples;
+                let target = self.target(&pixel, samples, &rounds);
+                for sample in taken..target {
+                    let mut sampler = Sampler::new(self.seed, index, sample);
+
+                    // The camera's v goes up, the rows go down
+                    let u = (x as f64 + sampler.next_f64()) / (self.width as f64 - 1.0);
+                    let v = (self.height as f64 - 1.0 - y as f64 + sampler.next_f64())
+                        / (self.height as f64 - 1.0);
+
+                    // Create a ray pointing from the camera to (x, y)
+                    let ray = self.camera.get_ray(u, v, &mut sampler);
+
+                    let first_hit = if self.find_first_hits {
+                        FirstHit::find(&ray, self.world)
+                    } else {
+                        FirstHit::default()
+                    };
+
+                    // Send the ray into the scene
+                    let color = ray.color(self.world, self.max_ray_depth, &mut sampler);
+                    pixel.add(&Pixel {
+                        color,
+                        first_hit,
+                        luminance_squares: color.luminance().powi(2),
+                        samples: 1,
+                    });
+                }
+                rendered.push(pixel);
+            }
+        }
+        rendered
+    }
+
+    /// The samples the pixels have after each round of `render`, the last is `samples`
+    ///
+    /// The rounds double the samples, from the least of the adaptive sampling or from one,
+    /// but add at most `MAX_ROUND_SAMPLES`
+    pub fn rounds(&self, samples: usize) -> Vec<usize> {
+        let mut rounds = Vec::new();
+        let mut round = self
+            .adaptive
+            .map_or(1, |adaptive| adaptive.min_samples.max(1));
+        while round < samples {
+            rounds.push(round);
+            round += round.min(MAX_ROUND_SAMPLES);
+        }
+        rounds.push(samples);
+        rounds
+    }
+
+    /// The samples the pixel should have, `rounds` are those of `samples`
+    ///
+    /// The adaptive sampling only stops a pixel at the end of a round,
+    /// so the rendered and the progressive pictures have the same samples
+    fn target(&self, pixel: &Pixel, samples: usize, rounds: &[usize]) -> usize {
+        match &self.adaptive {
+            Some(adaptive)
+                if rounds.binary_search(&pixel.samples).is_ok() && adaptive.converged(pixel) =>
+            {
+                pixel.samples
+            }
+            _ => samples.max(pixel.samples),
+        }
+    }
+
+    /// Whether any of the pixels needs more samples to have `samples`
+    fn any_active(&self, pixels: &[Pixel], samples: usize) -> bool {
+        let rounds = self.rounds(samples);
+        pixels
+            .iter()
+            .any(|pixel| self.target(pixel, samples, &rounds) > pixel.samples)
+    }
+
+    /// Add samples to the pixels, row by row from the top, until each has `samples`
+    ///
+    /// Goes in rounds, the adaptive sampling doubles the samples of the noisy pixels in each.
+    /// `between_rounds` gets the sums after all but the last one, to write checkpoints.
+    /// The samples are the same however they are split, so the rounds don't change the picture
+    pub fn render(
+        &self,
+        pixels: &mut [Pixel],
+        samples: usize,
+        mut between_rounds: impl FnMut(&[Pixel]),
+    ) {
+        let rounds = self.rounds(samples);
+        let progress = Progress::new(self.tiles().len() * rounds.len(), None);
+        for (i, &round) in rounds.iter().enumerate() {
+            self.render_tiles(pixels, round, &progress, &|| false);
+            if i + 1 == rounds.len() || !self.any_active(pixels, samples) {
+                break;
+            }
+            between_rounds(pixels);
+        }
+        progress.finish();
+    }
+
+    /// Add one sample per pixel at a time, and hand the sums to `snapshot` now and then
+    ///
+    /// Stops once every pixel has `samples` or has converged, at the time limit or at Ctrl+C.
+    /// A pass may be cut short, so the pixels can have different numbers of samples.
+    /// The adaptive sampling stops the pixels at the ends of the rounds of `render`,
+    /// so a finished progressive render has the same samples
+    pub fn render_progressive(
+        &self,
+        pixels: &mut [Pixel],
+        samples: usize,
+        options: &Progressive,
+        mut snapshot: impl FnMut(&[Pixel]),
+    ) {
+        interrupt::install();
+        let deadline = options
+            .time_limit
+            .map(|seconds| Instant::now() + Duration::from_secs_f64(seconds));
+        let stop = || interrupt::requested() || deadline.is_some_and(|d| Instant::now() >= d);
+
+        // A continued render starts with its least sampled pixels which aren't done
+        let rounds = self.rounds(samples);
+        let first = pixels
+            .iter()
+            .filter(|pixel| self.target(pixel, samples, &rounds) > pixel.samples)
+            .map(|pixel| pixel.samples)
+            .min()
+            .unwrap_or(samples)
+            + 1;
+        let passes = (samples + 1).saturating_sub(first);
+        let progress = Progress::new(self.tiles().len() * passes, deadline);
+        let mut last_snapshot = Instant::now();
+        for pass in first..=samples {
+            self.render_tiles(pixels, pass, &progress, &stop);
+            if stop() || pass == samples || !self.any_active(pixels, samples) {
+                break;
+            }
+            let passes_due = options.snapshot_passes.is_some_and(|n| pass % n == 0);
+            let time_due = options
+                .snapshot_seconds
+                .is_some_and(|seconds| last_snapshot.elapsed().as_secs_f64() >= seconds);
+            if passes_due || time_due {
+                snapshot(pixels);
+                last_snapshot = Instant::now();
+            }
+        }
+        progress.finish();
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        Tile::split(self.width, self.height, TILE_SIZE)
+    }
+
+    /// Render the missing samples of every tile into the pixels
+    ///
+    /// The tiles are shared between the threads by rayon's work stealing.
+    /// Once `stop` returns true the remaining tiles are skipped
+    fn render_tiles(
+        &self,
+        pixels: &mut [Pixel],
+        samples: usize,
+        progress: &Progress,
+        stop: &(dyn Fn() -> bool + Sync),
+    ) {
+        let tiles = self.tiles();
+        let rendered: Vec<_> = tiles
+            .par_iter()
+            .map(|tile| {
+                if stop() {
+                    return None;
+                }
+                let rays = ray::rays_traced();
+                let tile_pixels = self.render_tile(tile, pixels, samples);
+                progress.tile_done(ray::rays_traced() - rays);
+                Some(tile_pixels)
+            })
+            .collect();
+
+        for (tile, tile_pixels) in tiles.iter().zip(rendered) {
+            for (i, pixel) in tile_pixels.into_iter().flatten().enumerate() {
+                let (x, y) = (tile.x + i % tile.width, tile.y + i / tile.width);
+                pixels[y * self.width + x] = pixel;
+            }
+        }
+    }
+}
+
+/// Shows the finished tiles, the remaining time and the speed on a progress bar
+struct Progress {
+    total: usize,
+    start: Instant,
+    /// The render stops at the time limit, even if there are tiles left
+    deadline: Option<Instant>,
+    state: Mutex<ProgressState>,
+}
+
+struct ProgressState {
+    bar: progress::Bar,
+    tiles: usize,
+    rays: u64,
+}
+
+impl Progress {
+    fn new(total: usize, deadline: Option<Instant>) -> Self {
+        let mut bar = progress::Bar::new();
+        bar.set_job_title("Rendering...");
+        Self {
+            total,
+            start: Instant::now(),
+            deadline,
+            state: Mutex::new(ProgressState {
+                bar,
+                tiles: 0,
+                rays: 0,
+            }),
+        }
+    }
+
+    fn tile_done(&self, rays: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.tiles += 1;
+        state.rays += rays;
+
+        let elapsed = self.start.elapsed();
+        // The finished tiles predict the rest
+        let mut eta = elapsed.mul_f64((self.total - state.tiles) as f64 / state.tiles as f64);
+        if let Some(deadline) = self.deadline {
+            eta = eta.min(deadline.saturating_duration_since(Instant::now()));
+        }
+        let rays_per_second = state.rays as f64 / elapsed.as_secs_f64().max(1e-9);
+        let title = format!(
+            "{}/{} tiles {:.2} Mrays/s ETA {}",
+            state.tiles,
+            self.total,
+            rays_per_second / 1e6,
+            format_duration(eta)
+        );
+        let percent = (100 * state.tiles / self.total) as i32;
+        state.bar.set_job_title(&title);
+        state.bar.reach_percent(percent);
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.bar.jobs_done();
+        let elapsed = self.start.elapsed();
+        eprintln!(
+            " render time:      {} ({:.2} Mrays/s)",
+            format_duration(elapsed),
+            state.rays as f64 / elapsed.as_secs_f64().max(1e-9) / 1e6
+        );
+    }
+}
+
+/// Like 1:05:09 or 2:07
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_cover_the_picture() {
+        let tiles = Tile::split(70, 40, 32);
+        assert_eq!(tiles.len(), 3 * 2);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x: 64,
+                y: 0,
+                width: 6,
+                height: 32
+            }
+        );
+        let area: usize = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(area, 70 * 40);
+    }
+
+    #[test]
+    fn pixels_average_their_own_samples() {
+        let mut pixel = Pixel::default();
+        assert_eq!(pixel.average(), Color::black());
+
+        let sample = |value, object_id| Pixel {
+            color: Color::new(value, value, value),
+            first_hit: FirstHit {
+                object_id,
+                ..FirstHit::default()
+            },
+            samples: 1,
+            ..Pixel::default()
+        };
+        pixel.add(&sample(1.0, 3));
+        pixel.add(&sample(2.0, 5));
+        assert_eq!(pixel.average(), Color::new(1.5, 1.5, 1.5));
+        assert_eq!(pixel.first_hit.object_id, 3);
+    }
+
+    #[test]
+    fn pixels_get_the_missing_samples() {
+        use crate::{environment::Uniform, HittableList, Point};
+
+        let sky = Color::new(0.25, 0.5, 1.0);
+        let world = World {
+            objects: Box::new(HittableList::default()),
+            lights: HittableList::default(),
+            environment: Box::new(Uniform::new(sky)),
+        };
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            3.0,
+        );
+        let renderer = Renderer {
+            camera: &camera,
+            world: &world,
+            width: 3,
+            height: 1,
+            max_ray_depth: 5,
+            find_first_hits: false,
+            adaptive: None,
+            seed: 0,
+        };
+
+        // Continued from a pass which was cut short
+        let mut pixels: Vec<_> = [0, 3, 5]
+            .iter()
+            .map(|&samples| Pixel {
+                color: samples as f64 * sky,
+                samples,
+                ..Pixel::default()
+            })
+            .collect();
+        renderer.render(&mut pixels, 4, |_| {});
+        let samples: Vec<_> = pixels.iter().map(|p| p.samples).collect();
+        assert_eq!(samples, [4, 4, 5]);
+        assert_eq!(pixels[0].color, 4.0 * sky);
+        assert_eq!(pixels[1].average(), sky);
+    }
+
+    /// A diffuse sphere under the sky gradient, seen from a 4:3 camera
+    fn sphere_scene() -> (World, Camera) {
+        use crate::{environment::Gradient, material::Lambertian, HittableList, Point, Sphere};
+        use std::sync::Arc;
+
+        let mut objects = HittableList::default();
+        objects.add(Box::new(Sphere::new(
+            Point::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3))),
+        )));
+        let world = World {
+            objects: Box::new(objects),
+            lights: HittableList::default(),
+            environment: Box::new(Gradient::new(Color::white(), Color::new(0.5, 0.7, 1.0))),
+        };
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+            90.0,
+            4.0 / 3.0,
+        );
+        (world, camera)
+    }
+
+    #[test]
+    fn same_seed_same_picture() {
+        let (world, camera) = sphere_scene();
+        let render = |seed, passes: &[usize]| {
+            let renderer = Renderer {
+                camera: &camera,
+                world: &world,
+                width: 4,
+                height: 3,
+                max_ray_depth: 5,
+                find_first_hits: true,
+                adaptive: None,
+                seed,
+            };
+            let mut pixels = vec![Pixel::default(); 12];
+            for &samples in passes {
+                renderer.render(&mut pixels, samples, |_| {});
+            }
+            pixels
+        };
+
+        let pixels = render(7, &[6]);
+        assert_eq!(render(7, &[6]), pixels);
+        // Continuing takes the same samples and adds them in the same order
+        assert_eq!(render(7, &[3, 6]), pixels);
+        assert_ne!(render(8, &[6]), pixels);
+    }
+
+    #[test]
+    fn progressive_has_the_samples_of_the_rounds() {
+        let (world, camera) = sphere_scene();
+        let renderer = Renderer {
+            camera: &camera,
+            world: &world,
+            width: 8,
+            height: 6,
+            max_ray_depth: 5,
+            find_first_hits: false,
+            adaptive: Some(Adaptive {
+                threshold: 0.05,
+                min_samples: 4,
+            }),
+            seed: 2,
+        };
+        assert_eq!(renderer.rounds(40), [4, 8, 16, 32, 40]);
+
+        let mut rendered = vec![Pixel::default(); 48];
+        renderer.render(&mut rendered, 40, |_| {});
+        let mut progressive = vec![Pixel::default(); 48];
+        renderer.render_progressive(&mut progressive, 40, &Progressive::default(), |_| {});
+        assert_eq!(progressive, rendered);
+
+        let samples: Vec<_> = rendered.iter().map(|p| p.samples).collect();
+        // The sky stops early, the sphere is noisier
+        assert!(samples.contains(&4) && samples.contains(&40));
+        assert!(samples.iter().all(|s| [4, 8, 16, 32, 40].contains(s)));
+    }
+
+    #[test]
+    fn noisy_pixels_are_not_converged() {
+        let adaptive = Adaptive {
+            threshold: 0.05,
+            min_samples: 4,
+        };
+        let mut flat = Pixel::default();
+        let mut noisy = Pixel::default();
+        for i in 0..8 {
+            let sample = |value: f64| Pixel {
+                color: Color::new(value, value, value),
+                luminance_squares: value * value,
+                samples: 1,
+                ..Pixel::default()
+            };
+            flat.add(&sample(0.5));
+            noisy.add(&sample(if i % 2 == 0 { 0.0 } else { 1.0 }));
+            if i < 3 {
+                assert!(!adaptive.converged(&flat), "too few samples");
+            }
+        }
+        assert!(flat.standard_error().abs() < 1e-9);
+        assert!(adaptive.converged(&flat));
+        // The variance of 0 and 1 is 2/7 with Bessel's correction
+        assert!((noisy.standard_error() - (2.0 / 7.0 / 8.0f64).sqrt()).abs() < 1e-9);
+        assert!(!adaptive.converged(&noisy));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_secs(127)), "2:07");
+        assert_eq!(format_duration(Duration::from_secs(3909)), "1:05:09");
+    }
+}
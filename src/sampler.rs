@@ -0,0 +1,80 @@
+/// Deterministic random numbers for one sample of one pixel
+///
+/// Seeded by the seed of the render, the pixel and the index of the sample,
+/// so a sample comes out the same whichever thread renders it and in whatever order,
+/// and a continued render takes the same samples as an uninterrupted one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64, pixel: usize, sample: usize) -> Self {
+        let state = mix(mix(mix(seed) ^ pixel as u64) ^ sample as u64);
+        Self { state }
+    }
+
+    /// SplitMix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill the mantissa
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [min, max)
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    /// Uniform below `n`, which mustn't be zero
+    pub fn index(&mut self, n: usize) -> usize {
+        ((self.next_f64() * n as f64) as usize).min(n - 1)
+    }
+}
+
+/// The finalizer of SplitMix64, every bit of the input changes half of the output
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let numbers = |seed, pixel, sample| {
+            let mut sampler = Sampler::new(seed, pixel, sample);
+            (0..4).map(|_| sampler.next_f64()).collect::<Vec<_>>()
+        };
+        assert_eq!(numbers(1, 2, 3), numbers(1, 2, 3));
+        assert_ne!(numbers(1, 2, 3), numbers(2, 2, 3));
+        assert_ne!(numbers(1, 2, 3), numbers(1, 3, 3));
+        assert_ne!(numbers(1, 2, 3), numbers(1, 2, 4));
+    }
+
+    #[test]
+    fn uniform() {
+        let mut sampler = Sampler::new(0, 0, 0);
+        let n = 100_000;
+        let mut sum = 0.0;
+        let mut counts = [0; 4];
+        for _ in 0..n {
+            let x = sampler.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            sum += x;
+            counts[sampler.index(4)] += 1;
+        }
+        assert!((sum / n as f64 - 0.5).abs() < 0.01);
+        assert!(counts
+            .iter()
+            .all(|&c| (c as f64 / n as f64 - 0.25).abs() < 0.01));
+    }
+}
@@ -0,0 +1,467 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::output::ToneOperator;
+use crate::texture::{Pattern, Wrap};
+use crate::{Color, Point};
+
+/// The content of a scene file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    pub render: RenderSettings,
+    pub environment: EnvironmentDescription,
+    /// Textures referenced by the materials by name
+    pub textures: BTreeMap<String, TextureDescription>,
+    /// Materials referenced by the objects by name
+    pub materials: BTreeMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+    /// The scene file, relative paths are resolved from its directory
+    ///
+    /// Empty for the default scene
+    pub file: PathBuf,
+}
+
+/// Where the camera is and where it looks
+///
+/// Also settable from the command line, which takes precedence
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraDescription {
+    #[serde(alias = "origin")]
+    pub look_from: Point,
+    pub look_at: Point,
+    /// The direction which is up on the image
+    pub vup: Point,
+    /// Vertical field of view in degrees
+    pub vfov: f64,
+    /// Diameter of the lens, zero for a pinhole camera without depth of field
+    pub aperture: f64,
+    /// Distance of the sharp plane [default: distance of look_at]
+    pub focus_distance: Option<f64>,
+    /// Focus on the first object in the center of the image
+    pub autofocus: bool,
+    /// Number of aperture blades for polygonal bokeh, round if not given
+    pub blades: Option<u32>,
+    /// Rotation of the blades in degrees
+    pub blade_rotation: f64,
+    /// When the shutter opens, in frames
+    pub shutter_open: f64,
+    /// When the shutter closes, in frames
+    pub shutter_close: f64,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        Self {
+            look_from: Point::new(0.0, 0.0, 0.0),
+            look_at: Point::new(0.0, 0.0, -1.0),
+            vup: Point::new(0.0, 1.0, 0.0),
+            vfov: 90.0,
+            aperture: 0.0,
+            focus_distance: None,
+            autofocus: false,
+            blades: None,
+            blade_rotation: 0.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+        }
+    }
+}
+
+impl CameraDescription {
+    /// Check the settings which can't make a camera
+    ///
+    /// Returns the name of the offending field and a message
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err((
+                "vfov",
+                String::from("Expected an angle between 0 and 180 degrees"),
+            ));
+        }
+        if self.aperture < 0.0 {
+            return Err(("aperture", String::from("Expected a non negative value")));
+        }
+        if matches!(self.focus_distance, Some(d) if d <= 0.0) {
+            return Err(("focus_distance", String::from("Expected a positive value")));
+        }
+        if matches!(self.blades, Some(blades) if blades < 3) {
+            return Err(("blades", String::from("Expected at least 3 blades")));
+        }
+        if self.shutter_close < self.shutter_open {
+            return Err((
+                "shutter_close",
+                String::from("The shutter can't close before it opens"),
+            ));
+        }
+        let view = self.look_at - self.look_from;
+        if view.near_zero() {
+            return Err((
+                "look_at",
+                String::from("The camera can't look at its own position"),
+            ));
+        }
+        if Point::cross(&view, &self.vup).near_zero() {
+            return Err((
+                "vup",
+                String::from("Expected a direction which is not parallel to the view"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Settings which can also be given as command line arguments
+///
+/// The command line arguments take precedence
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderSettings {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+    pub ray_depth: Option<usize>,
+    pub output: Option<String>,
+    /// In stops, for the 8-bit outputs
+    pub exposure: Option<f64>,
+    pub tone_map: Option<ToneOperator>,
+    /// The brightness which becomes white for some tone operators
+    pub white_point: Option<f64>,
+    /// Turn off the sky for interiors lit by emissive materials
+    pub sky: Option<bool>,
+}
+
+/// The light from where the rays don't hit anything, selected by the `type` key of its table
+///
+/// The sky gradient by default
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvironmentDescription {
+    Gradient(GradientDescription),
+    Uniform(UniformDescription),
+    Map(MapDescription),
+    Sky(SkyDescription),
+}
+
+impl Default for EnvironmentDescription {
+    fn default() -> Self {
+        EnvironmentDescription::Gradient(GradientDescription::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GradientDescription {
+    /// Color of the nadir, the horizon is halfway to the top
+    pub bottom: Color,
+    /// Color of the zenith
+    pub top: Color,
+}
+
+impl Default for GradientDescription {
+    fn default() -> Self {
+        Self {
+            bottom: Color::white(),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UniformDescription {
+    pub color: Color,
+}
+
+/// An equirectangular Radiance `.hdr` picture of the surroundings
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapDescription {
+    /// Relative to the scene file
+    pub path: PathBuf,
+    /// Turn around the y axis in degrees
+    #[serde(default)]
+    pub rotation: f64,
+    /// Multiplies the pixels
+    #[serde(default = "MapDescription::default_intensity")]
+    pub intensity: f64,
+}
+
+impl MapDescription {
+    fn default_intensity() -> f64 {
+        1.0
+    }
+}
+
+/// Daylight sky of an analytic model with the sun in it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyDescription {
+    /// Towards the sun, which has to be above the horizon
+    pub sun_direction: Point,
+    /// Haziness of the air, from 1.7 (very clear) to 10 (hazy)
+    #[serde(default = "SkyDescription::default_turbidity")]
+    pub turbidity: f64,
+    /// Multiplies the sky and the sun
+    #[serde(default = "SkyDescription::default_intensity")]
+    pub intensity: f64,
+}
+
+impl SkyDescription {
+    fn default_turbidity() -> f64 {
+        3.0
+    }
+
+    fn default_intensity() -> f64 {
+        1.0
+    }
+}
+
+/// A constant color like `[1.0, 0.5, 0.0]` or the name of a texture
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged, expecting = "a color or the name of a texture")]
+pub enum ColorSource {
+    Color(Color),
+    Texture(String),
+}
+
+/// A texture, selected by the `type` key of its table
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextureDescription {
+    Solid(SolidDescription),
+    Checker(CheckerDescription),
+    Image(ImageDescription),
+    Noise(NoiseDescription),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolidDescription {
+    pub color: Color,
+}
+
+/// Alternating cubes in space
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckerDescription {
+    pub even: ColorSource,
+    pub odd: ColorSource,
+    /// Size of a cube
+    #[serde(default = "CheckerDescription::default_scale")]
+    pub scale: f64,
+}
+
+impl CheckerDescription {
+    fn default_scale() -> f64 {
+        1.0
+    }
+}
+
+/// A picture mapped by the texture coordinates
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageDescription {
+    /// Relative to the scene file
+    pub path: PathBuf,
+    /// `repeat` or `clamp`
+    #[serde(default)]
+    pub wrap: Wrap,
+}
+
+/// Procedural Perlin noise, no texture file is needed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseDescription {
+    /// `fbm`, `turbulence`, `marble` or `wood`
+    #[serde(default)]
+    pub pattern: Pattern,
+    /// Other seeds give other noise of the same look
+    #[serde(default)]
+    pub seed: u64,
+    /// Size of the features
+    #[serde(default = "NoiseDescription::default_scale")]
+    pub scale: f64,
+    /// More octaves add finer details
+    #[serde(default = "NoiseDescription::default_octaves")]
+    pub octaves: u32,
+    /// Where the noise is 0
+    #[serde(default = "NoiseDescription::default_low")]
+    pub low: ColorSource,
+    /// Where the noise is 1
+    #[serde(default = "NoiseDescription::default_high")]
+    pub high: ColorSource,
+}
+
+impl NoiseDescription {
+    fn default_scale() -> f64 {
+        1.0
+    }
+
+    fn default_octaves() -> u32 {
+        7
+    }
+
+    fn default_low() -> ColorSource {
+        ColorSource::Color(Color::black())
+    }
+
+    fn default_high() -> ColorSource {
+        ColorSource::Color(Color::white())
+    }
+}
+
+impl ColorSource {
+    /// Name of the texture, if it is not a constant color
+    pub fn texture(&self) -> Option<&String> {
+        match self {
+            ColorSource::Texture(name) => Some(name),
+            ColorSource::Color(_) => None,
+        }
+    }
+}
+
+impl TextureDescription {
+    /// Names of the other textures this one is made of
+    pub fn references(&self) -> Vec<&String> {
+        let sources = match self {
+            TextureDescription::Checker(checker) => vec![&checker.even, &checker.odd],
+            TextureDescription::Noise(noise) => vec![&noise.low, &noise.high],
+            TextureDescription::Solid(_) | TextureDescription::Image(_) => Vec::new(),
+        };
+        sources
+            .into_iter()
+            .filter_map(ColorSource::texture)
+            .collect()
+    }
+}
+
+/// A material, selected by the `type` key of its table
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialDescription {
+    Lambertian(LambertianDescription),
+    Metal(MetalDescription),
+    Dielectric(DielectricDescription),
+    DiffuseLight(DiffuseLightDescription),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LambertianDescription {
+    pub albedo: ColorSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetalDescription {
+    pub albedo: ColorSource,
+    #[serde(default)]
+    pub fuzziness: f64,
+}
+
+/// Glass is about 1.5, water is 1.33
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DielectricDescription {
+    pub refraction_index: f64,
+}
+
+/// An emissive material
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiffuseLightDescription {
+    /// Can be brighter than white
+    pub emit: Color,
+}
+
+/// An object, selected by the `type` key of its table
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectDescription {
+    Sphere(SphereDescription),
+    Triangle(TriangleDescription),
+    Mesh(MeshDescription),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SphereDescription {
+    pub center: Point,
+    pub radius: f64,
+    pub material: String,
+    /// How far the object moves during the frame, for motion blur
+    pub motion: Option<Point>,
+    pub transform: Option<TransformDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriangleDescription {
+    pub vertices: [Point; 3],
+    pub material: String,
+    /// How far the object moves during the frame
+    pub motion: Option<Point>,
+    pub transform: Option<TransformDescription>,
+}
+
+/// Triangle meshes of a Wavefront OBJ file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeshDescription {
+    /// Relative to the scene file
+    pub path: PathBuf,
+    /// Used by the faces which don't have a material in the file
+    pub material: Option<String>,
+    /// Only these groups of the file are loaded, all of them by default
+    pub groups: Option<Vec<String>>,
+    /// How far the object moves during the frame
+    pub motion: Option<Point>,
+    /// Transformed meshes of the same file share their triangles
+    pub transform: Option<TransformDescription>,
+}
+
+/// Places an object, it is scaled first,
+/// then rotated around the x, y and z axes, then translated
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransformDescription {
+    pub scale: Point,
+    /// Angles in degrees
+    pub rotate: Point,
+    pub translate: Point,
+}
+
+impl Default for TransformDescription {
+    fn default() -> Self {
+        Self {
+            scale: Point::new(1.0, 1.0, 1.0),
+            rotate: Point::default(),
+            translate: Point::default(),
+        }
+    }
+}
+
+impl ObjectDescription {
+    /// Name of the material, meshes may use only the materials of their files
+    pub fn material(&self) -> Option<&String> {
+        match self {
+            ObjectDescription::Sphere(sphere) => Some(&sphere.material),
+            ObjectDescription::Triangle(triangle) => Some(&triangle.material),
+            ObjectDescription::Mesh(mesh) => mesh.material.as_ref(),
+        }
+    }
+
+    pub fn motion(&self) -> Option<Point> {
+        match self {
+            ObjectDescription::Sphere(sphere) => sphere.motion,
+            ObjectDescription::Triangle(triangle) => triangle.motion,
+            ObjectDescription::Mesh(mesh) => mesh.motion,
+        }
+    }
+
+    pub fn transform(&self) -> Option<&TransformDescription> {
+        match self {
+            ObjectDescription::Sphere(sphere) => sphere.transform.as_ref(),
+            ObjectDescription::Triangle(triangle) => triangle.transform.as_ref(),
+            ObjectDescription::Mesh(mesh) => mesh.transform.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,894 @@
+mod description;
+mod parse;
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::camera::Lens;
+use crate::environment::{Environment, EnvironmentMap, Gradient, PhysicalSky, Uniform};
+use crate::material::{self, Material};
+use crate::mesh::obj::{self, ObjError};
+use crate::texture::{Checker, ImageTexture, Noise, Perlin, SolidColor, Texture};
+use crate::{
+    Bvh, Camera, Color, Hittable, HittableList, Instance, Moving, Point, Sphere, Transform,
+    Triangle,
+};
+
+pub use description::*;
+use parse::FieldError;
+
+/// The scene rendered when no scene file is given
+const DEFAULT_SCENE: &str = include_str!("../../scenes/default.toml");
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        file: PathBuf,
+        error: io::Error,
+    },
+    /// The scene file doesn't match the format or has an invalid value
+    Invalid {
+        file: PathBuf,
+        /// Path to the offending field, like `objects[2].radius`
+        field: String,
+        message: String,
+    },
+    Mesh(ObjError),
+    /// An image texture or environment map can't be read
+    Image {
+        file: PathBuf,
+        error: image::ImageError,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { file, error } => write!(f, "{}: {}", file.display(), error),
+            SceneError::Invalid {
+                file,
+                field,
+                message,
+            } => write!(f, "{}: {}: {}", file.display(), field, message),
+            SceneError::Mesh(error) => write!(f, "{}", error),
+            SceneError::Image { file, error } => write!(f, "{}: {}", file.display(), error),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { error, .. } => Some(error),
+            SceneError::Invalid { .. } => None,
+            SceneError::Mesh(error) => Some(error),
+            SceneError::Image { error, .. } => Some(error),
+        }
+    }
+}
+
+impl SceneDescription {
+    /// Read and validate a TOML scene file
+    pub fn load(file: &Path) -> Result<Self, SceneError> {
+        let source = std::fs::read_to_string(file).map_err(|error| SceneError::Io {
+            file: file.to_owned(),
+            error,
+        })?;
+        let mut scene = Self::parse(&source).map_err(|(field, message)| SceneError::Invalid {
+            file: file.to_owned(),
+            field,
+            message,
+        })?;
+        scene.file = file.to_owned();
+        Ok(scene)
+    }
+
+    pub fn default_scene() -> Self {
+        Self::parse(DEFAULT_SCENE).expect("The default scene is invalid")
+    }
+
+    fn parse(source: &str) -> Result<Self, FieldError> {
+        let scene = parse::parse(source)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Check the values which are well-formed, but still make no sense
+    fn validate(&self) -> Result<(), FieldError> {
+        let non_zero = |value: Option<usize>, field: &str| match value {
+            Some(0) => Err((
+                format!("render.{}", field),
+                String::from("Expected non zero value"),
+            )),
+            _ => Ok(()),
+        };
+        non_zero(self.render.width, "width")?;
+        non_zero(self.render.height, "height")?;
+        non_zero(self.render.samples, "samples")?;
+        non_zero(self.render.ray_depth, "ray_depth")?;
+
+        if matches!(self.render.white_point, Some(white) if white <= 0.0) {
+            return Err((
+                String::from("render.white_point"),
+                String::from("Expected a positive value"),
+            ));
+        }
+
+        self.camera
+            .validate()
+            .map_err(|(field, message)| (format!("camera.{}", field), message))?;
+
+        let intensity = match &self.environment {
+            EnvironmentDescription::Map(map) => map.intensity,
+            EnvironmentDescription::Sky(sky) => sky.intensity,
+            EnvironmentDescription::Gradient(_) | EnvironmentDescription::Uniform(_) => 1.0,
+        };
+        if intensity < 0.0 {
+            return Err((
+                String::from("environment.intensity"),
+                String::from("Expected a non negative value"),
+            ));
+        }
+        if let EnvironmentDescription::Sky(sky) = &self.environment {
+            if !(1.7..=10.0).contains(&sky.turbidity) {
+                return Err((
+                    String::from("environment.turbidity"),
+                    String::from("Expected a value between 1.7 and 10"),
+                ));
+            }
+            if sky.sun_direction.y() <= 0.0 {
+                return Err((
+                    String::from("environment.sun_direction"),
+                    String::from("The sun has to be above the horizon"),
+                ));
+            }
+        }
+
+        let known_texture = |source: &ColorSource, field: String| match source {
+            ColorSource::Texture(name) if !self.textures.contains_key(name) => {
+                Err((field, format!("Unknown texture `{}`", name)))
+            }
+            _ => Ok(()),
+        };
+
+        for (name, texture) in &self.textures {
+            let positive_scale = |scale: f64| {
+                if scale > 0.0 {
+                    Ok(())
+                } else {
+                    Err((
+                        format!("textures.{}.scale", name),
+                        String::from("Expected a positive value"),
+                    ))
+                }
+            };
+            match texture {
+                TextureDescription::Checker(checker) => {
+                    positive_scale(checker.scale)?;
+                    known_texture(&checker.even, format!("textures.{}.even", name))?;
+                    known_texture(&checker.odd, format!("textures.{}.odd", name))?;
+                }
+                TextureDescription::Noise(noise) => {
+                    positive_scale(noise.scale)?;
+                    if noise.octaves == 0 {
+                        return Err((
+                            format!("textures.{}.octaves", name),
+                            String::from("Expected non zero value"),
+                        ));
+                    }
+                    known_texture(&noise.low, format!("textures.{}.low", name))?;
+                    known_texture(&noise.high, format!("textures.{}.high", name))?;
+                }
+                TextureDescription::Solid(_) | TextureDescription::Image(_) => {}
+            }
+        }
+        for name in self.textures.keys() {
+            self.check_texture_cycle(name, name, 0)?;
+        }
+
+        for (name, material) in &self.materials {
+            match material {
+                MaterialDescription::Lambertian(LambertianDescription { albedo })
+                | MaterialDescription::Metal(MetalDescription { albedo, .. }) => {
+                    known_texture(albedo, format!("materials.{}.albedo", name))?;
+                }
+                _ => {}
+            }
+            match material {
+                MaterialDescription::Metal(metal) if !(0.0..=1.0).contains(&metal.fuzziness) => {
+                    return Err((
+                        format!("materials.{}.fuzziness", name),
+                        String::from("Expected a value between 0 and 1"),
+                    ));
+                }
+                MaterialDescription::Dielectric(dielectric)
+                    if dielectric.refraction_index <= 0.0 =>
+                {
+                    return Err((
+                        format!("materials.{}.refraction_index", name),
+                        String::from("Expected a positive value"),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            if let ObjectDescription::Sphere(sphere) = object {
+                if sphere.radius == 0.0 {
+                    return Err((
+                        format!("objects[{}].radius", i),
+                        String::from("Expected non zero value"),
+                    ));
+                }
+            }
+
+            if let Some(transform) = object.transform() {
+                let scale = transform.scale;
+                if scale.x() == 0.0 || scale.y() == 0.0 || scale.z() == 0.0 {
+                    return Err((
+                        format!("objects[{}].transform.scale", i),
+                        String::from("Expected non zero values"),
+                    ));
+                }
+            }
+
+            if let Some(material) = object.material() {
+                if !self.materials.contains_key(material) {
+                    return Err((
+                        format!("objects[{}].material", i),
+                        format!("Unknown material `{}`", material),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A texture can't be made of itself, not even through other textures
+    fn check_texture_cycle(&self, start: &str, name: &str, depth: usize) -> Result<(), FieldError> {
+        // Longer chains than the number of textures have to go around
+        if depth > self.textures.len() {
+            return Err((
+                format!("textures.{}", start),
+                String::from("The texture is made of itself"),
+            ));
+        }
+        for reference in self.textures[name].references() {
+            self.check_texture_cycle(start, reference, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// The world is needed for autofocus
+    pub fn camera(&self, aspect_ratio: f64, world: &dyn Hittable) -> Camera {
+        let description = &self.camera;
+        let lens = Lens {
+            aperture: description.aperture,
+            focus_distance: description
+                .focus_distance
+                .unwrap_or_else(|| (description.look_at - description.look_from).len()),
+            blades: description.blades,
+            rotation: description.blade_rotation,
+        };
+        let camera = Camera::new(
+            description.look_from,
+            description.look_at,
+            description.vup,
+            description.vfov,
+            aspect_ratio,
+        )
+        .with_lens(lens)
+        .with_shutter(description.shutter_open, description.shutter_close);
+
+        if description.autofocus {
+            camera.autofocus(world)
+        } else {
+            camera
+        }
+    }
+
+    /// Create the light from where the rays don't hit anything
+    ///
+    /// Environment maps are loaded from their files here
+    pub fn build_environment(&self) -> Result<Box<dyn Environment>, SceneError> {
+        Ok(match &self.environment {
+            EnvironmentDescription::Gradient(gradient) => {
+                Box::new(Gradient::new(gradient.bottom, gradient.top))
+            }
+            EnvironmentDescription::Uniform(uniform) => Box::new(Uniform::new(uniform.color)),
+            EnvironmentDescription::Map(map) => {
+                let directory = self.file.parent().unwrap_or_else(|| Path::new(""));
+                let file = directory.join(&map.path);
+                let environment = EnvironmentMap::load(&file)
+                    .map_err(|error| SceneError::Image { file, error })?;
+                Box::new(
+                    environment
+                        .with_rotation(map.rotation)
+                        .with_intensity(map.intensity),
+                )
+            }
+            EnvironmentDescription::Sky(sky) => Box::new(
+                PhysicalSky::new(sky.sun_direction, sky.turbidity).with_intensity(sky.intensity),
+            ),
+        })
+    }
+
+    /// Create the objects of the scene
+    ///
+    /// Returns every object and the emissive ones again, to sample them as lights.
+    /// Meshes are loaded from their files here
+    pub fn build_world(&self) -> Result<(HittableList, HittableList), SceneError> {
+        let mut textures = HashMap::new();
+        for name in self.textures.keys() {
+            self.texture(name, &mut textures)?;
+        }
+        let materials: HashMap<&str, Arc<dyn Material>> = self
+            .materials
+            .iter()
+            .map(|(name, material)| (name.as_ref(), material.build(&textures)))
+            .collect();
+
+        // Transformed meshes of the same file are instances of one BVH
+        let mut instanced: HashMap<_, Arc<dyn Hittable>> = HashMap::new();
+
+        let mut world = HittableList::default();
+        let mut lights = HittableList::default();
+        for (i, object) in self.objects.iter().enumerate() {
+            let emissive = self.is_emissive(object);
+            let mut shapes = match object.transform() {
+                Some(transform) if emissive => {
+                    // Every part of a light is an instance of its own, so they can be sampled one by one
+                    let transform = transform.build();
+                    let mut instances = HittableList::default();
+                    for part in self.shapes(i, object, &materials)?.into_objects() {
+                        let instance = Instance::new(Arc::from(part), transform);
+                        instances.add(Box::new(instance.with_id(i as u32 + 1)));
+                    }
+                    instances
+                }
+                Some(transform) => {
+                    let parts = match object {
+                        ObjectDescription::Mesh(mesh) => {
+                            let key = (&mesh.path, &mesh.material, &mesh.groups);
+                            match instanced.get(&key) {
+                                Some(parts) => parts.clone(),
+                                None => {
+                                    let shapes = self.shapes(i, object, &materials)?;
+                                    let parts: Arc<dyn Hittable> = Arc::new(Bvh::new(shapes));
+                                    instanced.insert(key, parts.clone());
+                                    parts
+                                }
+                            }
+                        }
+                        _ => Arc::new(Bvh::new(self.shapes(i, object, &materials)?)),
+                    };
+                    // The parts may be shared with other objects, which have other ids
+                    let instance = Instance::new(parts, transform.build()).with_id(i as u32 + 1);
+                    HittableList::default().chain_add(Box::new(instance))
+                }
+                None => self.shapes(i, object, &materials)?,
+            };
+
+            if let Some(displacement) = object.motion() {
+                let parts = shapes;
+                shapes = HittableList::default();
+                if emissive {
+                    // Every part of a light moves on its own, so they can be sampled one by one
+                    for part in parts.into_objects() {
+                        shapes.add(Box::new(Moving::new(part, displacement)));
+                    }
+                } else {
+                    // The parts of the object move together
+                    shapes.add(Box::new(Moving::new(
+                        Box::new(Bvh::new(parts)),
+                        displacement,
+                    )));
+                }
+            }
+
+            if emissive {
+                for shape in shapes.into_objects() {
+                    let shape: Arc<dyn Hittable> = Arc::from(shape);
+                    lights.add(Box::new(shape.clone()));
+                    world.add(Box::new(shape));
+                }
+            } else {
+                world.append(shapes);
+            }
+        }
+        Ok((world, lights))
+    }
+
+    /// Create a texture after the ones it is made of
+    ///
+    /// Textures used several times are created once, images are loaded from their files here
+    fn texture<'a>(
+        &'a self,
+        name: &'a str,
+        textures: &mut HashMap<&'a str, Arc<dyn Texture>>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some(texture) = textures.get(name) {
+            return Ok(texture.clone());
+        }
+        let mut source = |source: &'a ColorSource| match source {
+            ColorSource::Color(color) => Ok(Arc::new(SolidColor::new(*color)) as Arc<dyn Texture>),
+            ColorSource::Texture(name) => self.texture(name, textures),
+        };
+        let texture: Arc<dyn Texture> = match &self.textures[name] {
+            TextureDescription::Solid(solid) => Arc::new(SolidColor::new(solid.color)),
+            TextureDescription::Checker(checker) => Arc::new(Checker::new(
+                source(&checker.even)?,
+                source(&checker.odd)?,
+                checker.scale,
+            )),
+            TextureDescription::Image(image) => {
+                let directory = self.file.parent().unwrap_or_else(|| Path::new(""));
+                let file = directory.join(&image.path);
+                let texture = ImageTexture::load(&file, image.wrap)
+                    .map_err(|error| SceneError::Image { file, error })?;
+                Arc::new(texture)
+            }
+            TextureDescription::Noise(noise) => Arc::new(
+                Noise::new(Perlin::new(noise.seed), noise.pattern, noise.scale)
+                    .with_octaves(noise.octaves)
+                    .with_colors(source(&noise.low)?, source(&noise.high)?),
+            ),
+        };
+        textures.insert(name, texture.clone());
+        Ok(texture)
+    }
+
+    /// The primitives of an object, before it is moved or transformed
+    ///
+    /// Their object id is one more than the index of the object
+    fn shapes(
+        &self,
+        i: usize,
+        object: &ObjectDescription,
+        materials: &HashMap<&str, Arc<dyn Material>>,
+    ) -> Result<HittableList, SceneError> {
+        let id = i as u32 + 1;
+        let mut shapes = HittableList::default();
+        match object {
+            ObjectDescription::Sphere(sphere) => shapes.add(Box::new(
+                Sphere::new(
+                    sphere.center,
+                    sphere.radius,
+                    materials[sphere.material.as_str()].clone(),
+                )
+                .with_id(id),
+            )),
+            ObjectDescription::Triangle(triangle) => {
+                let [a, b, c] = triangle.vertices;
+                let material = materials[triangle.material.as_str()].clone();
+                shapes.add(Box::new(Triangle::new(a, b, c, material).with_id(id)))
+            }
+            ObjectDescription::Mesh(mesh) => {
+                let default_material = match &mesh.material {
+                    Some(material) => materials[material.as_str()].clone(),
+                    None => Arc::new(material::Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+                };
+                let directory = self.file.parent().unwrap_or_else(|| Path::new(""));
+                let groups = obj::load(&directory.join(&mesh.path), default_material)
+                    .map_err(SceneError::Mesh)?;
+                if let Some(names) = &mesh.groups {
+                    if let Some(missing) = names
+                        .iter()
+                        .find(|&name| !groups.iter().any(|g| &g.name == name))
+                    {
+                        return Err(SceneError::Invalid {
+                            file: self.file.clone(),
+                            field: format!("objects[{}].groups", i),
+                            message: format!("Unknown group `{}`", missing),
+                        });
+                    }
+                }
+                for group in groups {
+                    let selected = mesh
+                        .groups
+                        .as_ref()
+                        .is_none_or(|names| names.contains(&group.name));
+                    if selected {
+                        shapes.append(group.mesh.with_id(id).into_triangles());
+                    }
+                }
+            }
+        }
+        Ok(shapes)
+    }
+
+    /// Whether the object's material gives light
+    ///
+    /// The faces of a mesh with their own material in the file are sampled too,
+    /// which costs some shadow rays but gives no wrong light
+    fn is_emissive(&self, object: &ObjectDescription) -> bool {
+        matches!(
+            object.material().map(|name| &self.materials[name]),
+            Some(MaterialDescription::DiffuseLight(_))
+        )
+    }
+}
+
+impl TransformDescription {
+    fn build(&self) -> Transform {
+        let axes = [
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+        ];
+        let rotation = axes
+            .iter()
+            .enumerate()
+            .fold(Transform::identity(), |transform, (i, axis)| {
+                transform.then(&Transform::rotate(axis, self.rotate[i]))
+            });
+        Transform::scale(&self.scale)
+            .then(&rotation)
+            .then(&Transform::translate(&self.translate))
+    }
+}
+
+impl ColorSource {
+    /// The textures have to be created already
+    fn build(&self, textures: &HashMap<&str, Arc<dyn Texture>>) -> Arc<dyn Texture> {
+        match self {
+            ColorSource::Color(color) => Arc::new(SolidColor::new(*color)),
+            ColorSource::Texture(name) => textures[name.as_str()].clone(),
+        }
+    }
+}
+
+impl MaterialDescription {
+    fn build(&self, textures: &HashMap<&str, Arc<dyn Texture>>) -> Arc<dyn Material> {
+        match self {
+            MaterialDescription::Lambertian(lambertian) => Arc::new(
+                material::Lambertian::textured(lambertian.albedo.build(textures)),
+            ),
+            MaterialDescription::Metal(metal) => Arc::new(material::Metal::textured(
+                metal.albedo.build(textures),
+                metal.fuzziness,
+            )),
+            MaterialDescription::Dielectric(dielectric) => {
+                Arc::new(material::Dielectric::new(dielectric.refraction_index))
+            }
+            MaterialDescription::DiffuseLight(light) => {
+                Arc::new(material::DiffuseLight::new(light.emit))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::ToneOperator;
+    use crate::texture::Pattern;
+    use crate::{Ray, Sampler};
+
+    fn parse_error(source: &str) -> FieldError {
+        SceneDescription::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn default_scene() {
+        let scene = SceneDescription::default_scene();
+        assert_eq!(scene.materials.len(), 4);
+        assert_eq!(scene.objects.len(), 4);
+        let (objects, lights) = scene.build_world().unwrap();
+        assert_eq!(objects.into_objects().len(), 4);
+        assert!(lights.is_empty());
+    }
+
+    #[test]
+    fn emissive_objects_are_lights() {
+        let source = "
+            [materials.lamp]
+            type = \"DiffuseLight\"
+            emit = [4.0, 4.0, 4.0]
+
+            [materials.white]
+            type = \"Lambertian\"
+            albedo = [0.8, 0.8, 0.8]
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 2.0, -1.0]
+            radius = 0.5
+            material = \"lamp\"
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, -1.0]
+            radius = 0.5
+            material = \"white\"
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [2.0, 2.0, -1.0]
+            radius = 0.5
+            material = \"lamp\"
+            motion = [1.0, 0.0, 0.0]
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, 0.0]
+            radius = 0.5
+            material = \"lamp\"
+            transform = { scale = [2.0, 1.0, 1.0], translate = [-2.0, 2.0, -1.0] }
+        ";
+        let (objects, lights) = SceneDescription::parse(source)
+            .unwrap()
+            .build_world()
+            .unwrap();
+        assert_eq!(objects.into_objects().len(), 4);
+        // The moving lamp is sampled where it is at the time of the ray
+        let origin = Point::default();
+        let towards = Point::new(3.0, 2.0, -1.0);
+        assert!(lights.pdf_value(&origin, &towards, 1.0) > 0.0);
+        assert_eq!(lights.pdf_value(&origin, &towards, 0.0), 0.0);
+        // So is the transformed one
+        let towards = Point::new(-2.0, 2.0, -1.0);
+        assert!(lights.pdf_value(&origin, &towards, 0.0) > 0.0);
+        assert_eq!(lights.into_objects().len(), 3);
+    }
+
+    #[test]
+    fn render_settings() {
+        let scene = SceneDescription::parse("[render]\nwidth = 640\nsamples = 8\n").unwrap();
+        assert_eq!(scene.render.width, Some(640));
+        assert_eq!(scene.render.height, None);
+        assert_eq!(scene.render.samples, Some(8));
+
+        let source = "[render]\ntone_map = \"extended-reinhard\"\nexposure = -1.5\n";
+        let scene = SceneDescription::parse(source).unwrap();
+        assert_eq!(scene.render.tone_map, Some(ToneOperator::ExtendedReinhard));
+        assert_eq!(scene.render.exposure, Some(-1.5));
+        assert_eq!(scene.render.white_point, None);
+    }
+
+    #[test]
+    fn wrong_type_reports_field() {
+        let source = "
+            [materials.red]
+            type = \"Lambertian\"
+            albedo = [1.0, 0.0, 0.0]
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, -1.0]
+            radius = \"big\"
+            material = \"red\"
+        ";
+        assert_eq!(parse_error(source).0, "objects[0].radius");
+    }
+
+    #[test]
+    fn unknown_field_reports_field() {
+        assert_eq!(
+            parse_error("[camera]\norigni = [0, 0, 0]\n").0,
+            "camera.origni"
+        );
+    }
+
+    #[test]
+    fn unknown_material_reports_field() {
+        let source = "
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, -1.0]
+            radius = 0.5
+            material = \"glass\"
+        ";
+        assert_eq!(
+            parse_error(source),
+            (
+                String::from("objects[0].material"),
+                String::from("Unknown material `glass`")
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_value_reports_field() {
+        let source = "
+            [materials.steel]
+            type = \"Metal\"
+            albedo = [0.5, 0.5, 0.5]
+            fuzziness = 2.0
+        ";
+        assert_eq!(parse_error(source).0, "materials.steel.fuzziness");
+        assert_eq!(parse_error("[render]\nsamples = 0\n").0, "render.samples");
+    }
+
+    #[test]
+    fn transformed_object() {
+        let source = "
+            [materials.white]
+            type = \"Lambertian\"
+            albedo = [0.8, 0.8, 0.8]
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = \"white\"
+            transform = { scale = [1.0, 3.0, 1.0], translate = [0.0, 0.0, -5.0] }
+        ";
+        let (objects, _) = SceneDescription::parse(source)
+            .unwrap()
+            .build_world()
+            .unwrap();
+        let down = Ray::new(Point::new(0.0, 10.0, -5.0), Point::new(0.0, -1.0, 0.0));
+        let hit = objects.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!((hit.position - Point::new(0.0, 3.0, -5.0)).near_zero());
+
+        let flat = source.replace("scale = [1.0, 3.0, 1.0]", "scale = [1.0, 0.0, 1.0]");
+        assert_eq!(parse_error(&flat).0, "objects[0].transform.scale");
+    }
+
+    #[test]
+    fn object_ids_follow_the_order() {
+        let source = "
+            [materials.white]
+            type = \"Lambertian\"
+            albedo = [0.8, 0.8, 0.8]
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, -5.0]
+            radius = 1.0
+            material = \"white\"
+
+            [[objects]]
+            type = \"Sphere\"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = \"white\"
+            transform = { translate = [5.0, 0.0, -5.0] }
+        ";
+        let (objects, _) = SceneDescription::parse(source)
+            .unwrap()
+            .build_world()
+            .unwrap();
+        let id = |x| {
+            let ray = Ray::new(Point::new(x, 0.0, 0.0), Point::new(0.0, 0.0, -1.0));
+            objects.hit(&ray, 0.001, f64::INFINITY).unwrap().object_id
+        };
+        assert_eq!(id(0.0), 1);
+        assert_eq!(id(5.0), 2);
+    }
+
+    #[test]
+    fn textured_material() {
+        let source = "
+            [textures.tiles]
+            type = \"Checker\"
+            even = [1.0, 1.0, 1.0]
+            odd = \"red\"
+            scale = 0.5
+
+            [textures.red]
+            type = \"Solid\"
+            color = [1.0, 0.0, 0.0]
+
+            [materials.floor]
+            type = \"Lambertian\"
+            albedo = \"tiles\"
+        ";
+        let scene = SceneDescription::parse(source).unwrap();
+        assert_eq!(scene.textures["tiles"].references(), vec!["red"]);
+        assert!(scene.build_world().is_ok());
+
+        let unknown = source.replace("albedo = \"tiles\"", "albedo = \"wood\"");
+        assert_eq!(
+            parse_error(&unknown),
+            (
+                String::from("materials.floor.albedo"),
+                String::from("Unknown texture `wood`")
+            )
+        );
+
+        let cycle = source.replace("odd = \"red\"", "odd = \"tiles\"");
+        assert_eq!(parse_error(&cycle).0, "textures.tiles");
+    }
+
+    #[test]
+    fn noise_texture() {
+        let source = "
+            [textures.marble]
+            type = \"Noise\"
+            pattern = \"marble\"
+            seed = 42
+            high = [0.9, 0.9, 0.8]
+
+            [materials.stone]
+            type = \"Metal\"
+            albedo = \"marble\"
+        ";
+        let scene = SceneDescription::parse(source).unwrap();
+        match &scene.textures["marble"] {
+            TextureDescription::Noise(noise) => {
+                assert_eq!(noise.pattern, Pattern::Marble);
+                assert_eq!(noise.seed, 42);
+                assert_eq!(noise.scale, 1.0);
+                assert_eq!(noise.low, ColorSource::Color(Color::black()));
+            }
+            texture => panic!("Expected a noise texture, got {:?}", texture),
+        }
+        assert!(scene.build_world().is_ok());
+
+        let flat = source.replace("seed = 42", "octaves = 0");
+        assert_eq!(parse_error(&flat).0, "textures.marble.octaves");
+        let unknown = source.replace(
+            "\"marble\"\n            seed",
+            "\"granite\"\n            seed",
+        );
+        assert_eq!(parse_error(&unknown).0, "textures.marble.pattern");
+    }
+
+    #[test]
+    fn environment_map() {
+        use image::codecs::hdr::HdrEncoder;
+
+        // Bright above the horizon, dark below
+        let (width, height) = (4, 2);
+        let pixels: Vec<_> = (0..width * height)
+            .map(|i| match i / width {
+                0 => image::Rgb([2.0f32, 4.0, 8.0]),
+                _ => image::Rgb([0.0f32, 0.0, 0.0]),
+            })
+            .collect();
+        let directory = std::env::temp_dir();
+        let path = directory.join(format!("environment-{}.hdr", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        HdrEncoder::new(file)
+            .encode(&pixels, width, height)
+            .unwrap();
+
+        let source = format!(
+            "[environment]\ntype = \"Map\"\npath = {:?}\nintensity = 0.5\n",
+            path.file_name().unwrap()
+        );
+        let mut scene = SceneDescription::parse(&source).unwrap();
+        scene.file = directory.join("scene.toml");
+        let environment = scene.build_environment();
+        std::fs::remove_file(&path).unwrap();
+
+        let environment = environment.unwrap();
+        let up = environment.color(&Point::new(0.0, 1.0, 0.0));
+        assert_eq!(up, Color::new(1.0, 2.0, 4.0));
+        assert_eq!(
+            environment.color(&Point::new(0.0, -1.0, 0.0)),
+            Color::black()
+        );
+        assert!(environment.random(&mut Sampler::new(0, 0, 0)).unwrap().y() > 0.0);
+
+        let negative = source.replace("0.5", "-1.0");
+        assert_eq!(parse_error(&negative).0, "environment.intensity");
+    }
+
+    #[test]
+    fn physical_sky() {
+        let source = "
+            [environment]
+            type = \"Sky\"
+            sun_direction = [1.0, 0.5, 0.0]
+        ";
+        let scene = SceneDescription::parse(source).unwrap();
+        let sky = scene.build_environment().unwrap();
+        assert!(sky.is_sampled());
+        assert!(sky.pdf_value(&sky.random(&mut Sampler::new(0, 0, 0)).unwrap()) > 0.0);
+
+        let hazy = source.replace("0.0]", "0.0]\nturbidity = 12.0");
+        assert_eq!(parse_error(&hazy).0, "environment.turbidity");
+        let night = source.replace("0.5", "-0.5");
+        assert_eq!(parse_error(&night).0, "environment.sun_direction");
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::BTreeMap;
+use toml::Value;
+
+use super::description::*;
+
+/// An error as the path of the offending field and a message
+pub type FieldError = (String, String);
+
+type VariantResult<T> = Result<T, serde_path_to_error::Error<toml::de::Error>>;
+
+/// The scene file before the tagged tables are resolved
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScene {
+    #[serde(default)]
+    camera: CameraDescription,
+    #[serde(default)]
+    render: RenderSettings,
+    environment: Option<Value>,
+    #[serde(default)]
+    textures: BTreeMap<String, Value>,
+    #[serde(default)]
+    materials: BTreeMap<String, Value>,
+    #[serde(default)]
+    objects: Vec<Value>,
+}
+
+pub fn parse(source: &str) -> Result<SceneDescription, FieldError> {
+    let deserializer = &mut toml::Deserializer::new(source);
+    let raw: RawScene = serde_path_to_error::deserialize(deserializer)
+        .map_err(|err| (err.path().to_string(), err.inner().to_string()))?;
+
+    let environment = match raw.environment {
+        Some(value) => tagged(value, "environment", EnvironmentDescription::from_tag)?,
+        None => EnvironmentDescription::default(),
+    };
+
+    let textures = raw
+        .textures
+        .into_iter()
+        .map(|(name, value)| {
+            let path = format!("textures.{}", name);
+            Ok((name, tagged(value, &path, TextureDescription::from_tag)?))
+        })
+        .collect::<Result<_, FieldError>>()?;
+
+    let materials = raw
+        .materials
+        .into_iter()
+        .map(|(name, value)| {
+            let path = format!("materials.{}", name);
+            Ok((name, tagged(value, &path, MaterialDescription::from_tag)?))
+        })
+        .collect::<Result<_, FieldError>>()?;
+
+    let objects = raw
+        .objects
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            tagged(
+                value,
+                &format!("objects[{}]", i),
+                ObjectDescription::from_tag,
+            )
+        })
+        .collect::<Result<_, FieldError>>()?;
+
+    Ok(SceneDescription {
+        camera: raw.camera,
+        render: raw.render,
+        environment,
+        textures,
+        materials,
+        objects,
+        file: Default::default(),
+    })
+}
+
+/// Deserialize a table which selects its variant with a `type` key
+///
+/// Serde buffers internally tagged enums, which hides the path of the offending field.
+/// So the `type` is looked up by hand, then the rest is deserialized as that variant.
+fn tagged<T>(
+    value: Value,
+    path: &str,
+    from_tag: fn(&str, Value) -> Option<VariantResult<T>>,
+) -> Result<T, FieldError> {
+    let mut table = match value {
+        Value::Table(table) => table,
+        other => {
+            let message = format!("invalid type: {}, expected a table", other.type_str());
+            return Err((path.to_owned(), message));
+        }
+    };
+
+    let tag = match table.remove("type") {
+        Some(Value::String(tag)) => tag,
+        Some(other) => {
+            let message = format!("invalid type: {}, expected a string", other.type_str());
+            return Err((format!("{}.type", path), message));
+        }
+        None => return Err((path.to_owned(), String::from("missing field `type`"))),
+    };
+
+    match from_tag(&tag, Value::Table(table)) {
+        Some(Ok(variant)) => Ok(variant),
+        Some(Err(err)) => {
+            let field = match err.path().to_string().as_ref() {
+                "." => path.to_owned(),
+                inner => format!("{}.{}", path, inner),
+            };
+            Err((field, err.inner().to_string()))
+        }
+        None => Err((format!("{}.type", path), format!("unknown type `{}`", tag))),
+    }
+}
+
+fn variant<T: DeserializeOwned>(value: Value) -> VariantResult<T> {
+    serde_path_to_error::deserialize(value)
+}
+
+impl EnvironmentDescription {
+    fn from_tag(tag: &str, value: Value) -> Option<VariantResult<Self>> {
+        Some(match tag {
+            "Gradient" => variant(value).map(EnvironmentDescription::Gradient),
+            "Uniform" => variant(value).map(EnvironmentDescription::Uniform),
+            "Map" => variant(value).map(EnvironmentDescription::Map),
+            "Sky" => variant(value).map(EnvironmentDescription::Sky),
+            _ => return None,
+        })
+    }
+}
+
+impl TextureDescription {
+    fn from_tag(tag: &str, value: Value) -> Option<VariantResult<Self>> {
+        Some(match tag {
+            "Solid" => variant(value).map(TextureDescription::Solid),
+            "Checker" => variant(value).map(TextureDescription::Checker),
+            "Image" => variant(value).map(TextureDescription::Image),
+            "Noise" => variant(value).map(TextureDescription::Noise),
+            _ => return None,
+        })
+    }
+}
+
+impl MaterialDescription {
+    fn from_tag(tag: &str, value: Value) -> Option<VariantResult<Self>> {
+        Some(match tag {
+            "Lambertian" => variant(value).map(MaterialDescription::Lambertian),
+            "Metal" => variant(value).map(MaterialDescription::Metal),
+            "Dielectric" => variant(value).map(MaterialDescription::Dielectric),
+            "DiffuseLight" => variant(value).map(MaterialDescription::DiffuseLight),
+            _ => return None,
+        })
+    }
+}
+
+impl ObjectDescription {
+    fn from_tag(tag: &str, value: Value) -> Option<VariantResult<Self>> {
+        Some(match tag {
+            "Sphere" => variant(value).map(ObjectDescription::Sphere),
+            "Triangle" => variant(value).map(ObjectDescription::Triangle),
+            "Mesh" => variant(value).map(ObjectDescription::Mesh),
+            _ => return None,
+        })
+    }
+}
@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
-use super::{HitRecord, Hittable, Point, Ray};
+use super::{Aabb, HitRecord, Hittable, Onb, Point, Ray, Sampler};
 use crate::material::Material;
 
 /// Sphere's body can be calculated
@@ -10,6 +10,8 @@ pub struct Sphere {
     center: Point,
     radius: f64,
     material: Arc<dyn Material>,
+    /// Given to the hit records
+    id: u32,
 }
 
 impl Sphere {
@@ -18,8 +20,24 @@ impl Sphere {
             center,
             radius,
             material,
+            id: 0,
         }
     }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Texture coordinates of a point of the unit sphere
+    ///
+    /// `u` goes around the y axis starting from -x,
+    /// `v` goes from the bottom (-y) to the top (+y)
+    fn uv(p: &Point) -> (f64, f64) {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
@@ -28,10 +46,10 @@ impl Hittable for Sphere {
     /// **if the discriminant is**
     ///
     /// - greater than 0,
-    /// then there are two intersections (front and back).
+    ///   then there are two intersections (front and back).
     ///
     /// - smaller than or equal to 0,
-    /// then the ray missed the object
+    ///   then the ray missed the object
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         // A sphere is given using x^2 + y^2 + z^2 <= r^2
         //
@@ -45,7 +63,7 @@ impl Hittable for Sphere {
         // then the discriminant < 0 (there are no solutions)
         let oc = *r.origin() - self.center;
         let a = r.direction().len_squared();
-        let half_b = Point::dot(&oc, &r.direction());
+        let half_b = Point::dot(&oc, r.direction());
         let c = oc.len_squared() - self.radius.powi(2);
         let discriminant = half_b.powi(2) - a * c;
 
@@ -61,10 +79,14 @@ impl Hittable for Sphere {
                 if t_min < t && t < t_max {
                     let position = r.point_at(t).unwrap();
                     let outward_normal = (position - self.center) / self.radius;
+                    let (u, v) = Self::uv(&((position - self.center) / self.radius.abs()));
                     let mut result = HitRecord {
                         t,
                         position,
                         material: self.material.clone(),
+                        u,
+                        v,
+                        object_id: self.id,
                         front_face: false,        // by set_front_face
                         normal: Point::default(), // by set_front_face
                     };
@@ -75,4 +97,92 @@ impl Hittable for Sphere {
         }
         None // All you had to do is follow the damn sphere Cray!
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The radius may be negative (hollow spheres)
+        let radius = Point::new(1.0, 1.0, 1.0) * self.radius.abs();
+        Some(Aabb::new(self.center - radius, self.center + radius))
+    }
+
+    /// Uniform over the cone of directions which see the sphere
+    fn pdf_value(&self, origin: &Point, direction: &Point, _time: f64) -> f64 {
+        let ray = Ray::new(*origin, *direction);
+        if self.hit(&ray, 0.001, f64::INFINITY).is_none() {
+            return 0.0;
+        }
+        let distance_squared = (self.center - *origin).len_squared();
+        if distance_squared <= self.radius.powi(2) {
+            // The whole sphere is around the origin, it can't be sampled
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: &Point, _time: f64, sampler: &mut Sampler) -> Option<Point> {
+        let direction = self.center - *origin;
+        let distance_squared = direction.len_squared();
+        if distance_squared <= self.radius.powi(2) {
+            return None;
+        }
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared).sqrt();
+
+        let (r1, r2) = (sampler.next_f64(), sampler.next_f64());
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * r1;
+        let sin_theta = (1.0 - z * z).sqrt();
+        let local = Point::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+
+        Some(Onb::from_w(&direction).local(&local))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn spherical_texture_coordinates() {
+        let uv = |x, y, z| Sphere::uv(&Point::new(x, y, z));
+        let near = |(u, v): (f64, f64), expected: (f64, f64)| {
+            (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12
+        };
+        assert!(near(uv(-1.0, 0.0, 0.0), (0.0, 0.5)));
+        assert!(near(uv(0.0, 0.0, 1.0), (0.25, 0.5)));
+        assert!(near(uv(1.0, 0.0, 0.0), (0.5, 0.5)));
+        assert!(near(uv(0.0, 0.0, -1.0), (0.75, 0.5)));
+        assert_eq!(uv(0.0, -1.0, 0.0).1, 0.0);
+        assert_eq!(uv(0.0, 1.0, 0.0).1, 1.0);
+    }
+
+    #[test]
+    fn sampled_directions_hit_the_sphere() {
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let sphere = Sphere::new(Point::new(1.0, 2.0, -3.0), 0.5, material);
+        let origin = Point::new(0.0, 0.0, 0.0);
+
+        let distance_squared = sphere.center.len_squared();
+        let cos_theta_max = (1.0 - 0.25 / distance_squared).sqrt();
+        let expected_pdf = 1.0 / (2.0 * PI * (1.0 - cos_theta_max));
+
+        let mut sampler = Sampler::new(0, 0, 0);
+        for _ in 0..1000 {
+            let direction = sphere.random(&origin, 0.0, &mut sampler).unwrap();
+            assert!(sphere
+                .hit(&Ray::new(origin, direction), 0.001, f64::INFINITY)
+                .is_some());
+            assert!((sphere.pdf_value(&origin, &direction, 0.0) - expected_pdf).abs() < 1e-9);
+        }
+        assert_eq!(
+            sphere.pdf_value(&origin, &Point::new(0.0, 0.0, 1.0), 0.0),
+            0.0
+        );
+
+        // Nothing to aim at from inside
+        let inside = Point::new(1.0, 2.2, -3.0);
+        assert!(sphere.random(&inside, 0.0, &mut sampler).is_none());
+    }
 }
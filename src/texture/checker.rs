@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use super::Texture;
+use crate::{Color, Point};
+
+/// Alternating cubes of two textures in space
+///
+/// Uses the position, so it doesn't need texture coordinates
+/// and isn't distorted at the poles of a sphere
+#[derive(Debug)]
+pub struct Checker {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    /// Size of a cube
+    scale: f64,
+}
+
+impl Checker {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: f64) -> Self {
+        Self { even, odd, scale }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Color {
+        let cell = |x: f64| (x / self.scale).floor() as i64;
+        let sum = cell(p.x()) + cell(p.y()) + cell(p.z());
+        if sum.rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn neighbour_cells_alternate() {
+        let checker = Checker::new(
+            Arc::new(SolidColor::new(Color::white())),
+            Arc::new(SolidColor::new(Color::black())),
+            0.5,
+        );
+        let at = |x, y, z| checker.value(0.0, 0.0, &Point::new(x, y, z));
+        assert_eq!(at(0.1, 0.1, 0.1), Color::white());
+        assert_eq!(at(0.6, 0.1, 0.1), Color::black());
+        assert_eq!(at(-0.1, 0.1, 0.1), Color::black());
+        assert_eq!(at(-0.1, -0.1, 0.1), Color::white());
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use super::Texture;
+use crate::color::srgb_decode;
+use crate::{Color, Point};
+
+/// What happens outside of the [0, 1] texture coordinates
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    /// The image is tiled
+    #[default]
+    Repeat,
+    /// The edge pixels are stretched
+    Clamp,
+}
+
+/// A picture mapped onto the surface by the texture coordinates
+///
+/// The pixels are interpolated with bilinear filtering
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Row by row from the top, already linear
+    pixels: Vec<Color>,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>, wrap: Wrap) -> Self {
+        assert!(width > 0 && height > 0, "The image is empty");
+        assert_eq!(pixels.len(), width * height, "One color per pixel");
+        Self {
+            width,
+            height,
+            pixels,
+            wrap,
+        }
+    }
+
+    /// Load an image in any format the `image` crate can read
+    pub fn load(path: &Path, wrap: Wrap) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.to_rgb();
+        // Pictures are stored in sRGB, the texture is linear
+        let channel = |value: u8| srgb_decode(value as f64 / 255.0);
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(channel(p[0]), channel(p[1]), channel(p[2])))
+            .collect();
+        Ok(Self::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+            wrap,
+        ))
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let index = |i: i64, size: usize| match self.wrap {
+            Wrap::Repeat => i.rem_euclid(size as i64) as usize,
+            Wrap::Clamp => i.clamp(0, size as i64 - 1) as usize,
+        };
+        self.pixels[index(y, self.height) * self.width + index(x, self.width)]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point) -> Color {
+        // Pixel centers are at half coordinates, v = 1 is the top row
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - fx) * self.texel(x0, y0) + fx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - fx) * self.texel(x0, y0 + 1) + fx * self.texel(x0 + 1, y0 + 1);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black on the left, white on the right
+    fn two_pixels(wrap: Wrap) -> ImageTexture {
+        ImageTexture::new(2, 1, vec![Color::black(), Color::white()], wrap)
+    }
+
+    #[test]
+    fn bilinear_filtering() {
+        let texture = two_pixels(Wrap::Clamp);
+        let at = |u| texture.value(u, 0.5, &Point::default());
+        assert_eq!(at(0.25), Color::black());
+        assert_eq!(at(0.5), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(at(0.75), Color::white());
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let clamped = two_pixels(Wrap::Clamp);
+        let repeated = two_pixels(Wrap::Repeat);
+        let p = Point::default();
+
+        // Past the right edge
+        assert_eq!(clamped.value(1.0, 0.5, &p), Color::white());
+        assert_eq!(repeated.value(1.0, 0.5, &p), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(repeated.value(1.25, 0.5, &p), Color::black());
+        assert_eq!(clamped.value(-3.0, 0.5, &p), Color::black());
+    }
+}
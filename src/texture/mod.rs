@@ -0,0 +1,22 @@
+mod checker;
+mod image_texture;
+mod noise;
+mod perlin;
+mod solid;
+
+use std::fmt::Debug;
+use std::marker::{Send, Sync};
+
+use crate::{Color, Point};
+
+/// A color which changes across a surface
+pub trait Texture: Debug + Sync + Send {
+    /// The color at the texture coordinates (u, v) and the hit position
+    fn value(&self, u: f64, v: f64, p: &Point) -> Color;
+}
+
+pub use checker::Checker;
+pub use image_texture::{ImageTexture, Wrap};
+pub use noise::{Noise, Pattern};
+pub use perlin::Perlin;
+pub use solid::SolidColor;
@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::{Perlin, SolidColor, Texture};
+use crate::{Color, Point};
+
+/// How the noise is turned into a look
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    /// Soft clouds
+    #[default]
+    Fbm,
+    /// Clouds with sharp creases
+    Turbulence,
+    /// Veins which repeat along the x axis, disturbed by turbulence
+    Marble,
+    /// Rings around the y axis, disturbed by fBm
+    Wood,
+}
+
+/// A procedural texture blending two textures by Perlin noise
+///
+/// Uses the position, so it doesn't need texture coordinates
+#[derive(Debug)]
+pub struct Noise {
+    perlin: Perlin,
+    pattern: Pattern,
+    /// Size of the features, the noise changes about once per unit
+    scale: f64,
+    octaves: u32,
+    /// Where the noise is 0
+    low: Arc<dyn Texture>,
+    /// Where the noise is 1
+    high: Arc<dyn Texture>,
+}
+
+impl Noise {
+    /// Black and white noise with 7 octaves
+    pub fn new(perlin: Perlin, pattern: Pattern, scale: f64) -> Self {
+        Self {
+            perlin,
+            pattern,
+            scale,
+            octaves: 7,
+            low: Arc::new(SolidColor::new(Color::black())),
+            high: Arc::new(SolidColor::new(Color::white())),
+        }
+    }
+
+    /// More octaves add finer details
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_colors(mut self, low: Arc<dyn Texture>, high: Arc<dyn Texture>) -> Self {
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    /// The amount of the high texture, between 0 and 1
+    fn blend(&self, p: &Point) -> f64 {
+        let p = *p / self.scale;
+        match self.pattern {
+            Pattern::Fbm => 0.5 * (1.0 + self.perlin.fbm(&p, self.octaves)),
+            Pattern::Turbulence => self.perlin.turbulence(&p, self.octaves),
+            Pattern::Marble => {
+                // The veins are further apart than the features of the turbulence
+                let turbulence = self.perlin.turbulence(&(0.2 * p), self.octaves);
+                0.5 * (1.0 + (p.x() + 5.0 * turbulence).sin())
+            }
+            Pattern::Wood => {
+                let radius = (p.x() * p.x() + p.z() * p.z()).sqrt();
+                let rings = 4.0 * radius + self.perlin.fbm(&p, self.octaves);
+                rings - rings.floor()
+            }
+        }
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Color {
+        let t = self.blend(p);
+        (1.0 - t) * self.low.value(u, v, p) + t * self.high.value(u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_blend_between_the_colors() {
+        for &pattern in &[
+            Pattern::Fbm,
+            Pattern::Turbulence,
+            Pattern::Marble,
+            Pattern::Wood,
+        ] {
+            let noise = Noise::new(Perlin::new(1), pattern, 0.3).with_octaves(4);
+            for i in 0..200 {
+                let p = Point::new(i as f64 * 0.13, i as f64 * 0.07, i as f64 * -0.21);
+                let t = noise.blend(&p);
+                assert!((0.0..=1.0).contains(&t), "{:?} gave {}", pattern, t);
+                let gray = noise.value(0.0, 0.0, &p);
+                assert!((gray[0] - t).abs() < 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::Point;
+
+const POINT_COUNT: usize = 256;
+
+/// Perlin gradient noise
+///
+/// Random unit gradients sit on the integer lattice,
+/// the noise smoothly blends their dot products with the offset from the corners.
+/// The same seed always gives the same noise
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    gradients: Vec<Point>,
+    /// Shuffled indices for each axis, they hash a lattice point into a gradient
+    permutations: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let p = Point::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                );
+                let len_squared = p.len_squared();
+                if 1e-6 < len_squared && len_squared <= 1.0 {
+                    break p.unit_vector();
+                }
+            })
+            .collect();
+        let mut permutation = || {
+            let mut indices: Vec<usize> = (0..POINT_COUNT).collect();
+            indices.shuffle(&mut rng);
+            indices
+        };
+        let permutations = [permutation(), permutation(), permutation()];
+        Self {
+            gradients,
+            permutations,
+        }
+    }
+
+    /// Smooth noise between -1 and 1, zero at the integer lattice points
+    pub fn noise(&self, p: &Point) -> f64 {
+        let cell = [p.x().floor(), p.y().floor(), p.z().floor()];
+        let offset = [p.x() - cell[0], p.y() - cell[1], p.z() - cell[2]];
+        // Hermite smoothing hides the lattice
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let weight = [smooth(offset[0]), smooth(offset[1]), smooth(offset[2])];
+
+        let mut sum = 0.0;
+        for corner in 0..8 {
+            let d = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let index = (0..3).fold(0, |hash, axis| {
+                let lattice = (cell[axis] as i64 + d[axis] as i64).rem_euclid(POINT_COUNT as i64);
+                hash ^ self.permutations[axis][lattice as usize]
+            });
+            let to_point = Point::new(
+                offset[0] - d[0] as f64,
+                offset[1] - d[1] as f64,
+                offset[2] - d[2] as f64,
+            );
+            let blend = (0..3).fold(1.0, |blend, axis| {
+                blend
+                    * if d[axis] == 1 {
+                        weight[axis]
+                    } else {
+                        1.0 - weight[axis]
+                    }
+            });
+            sum += blend * Point::dot(&self.gradients[index], &to_point);
+        }
+        sum.clamp(-1.0, 1.0)
+    }
+
+    /// Fractal Brownian motion, octaves of noise with doubling frequency and halving amplitude
+    ///
+    /// Between -1 and 1
+    pub fn fbm(&self, p: &Point, octaves: u32) -> f64 {
+        self.octaves(p, octaves, |noise| noise)
+    }
+
+    /// Like fBm, but the octaves are folded at zero which gives sharp creases
+    ///
+    /// Between 0 and 1
+    pub fn turbulence(&self, p: &Point, octaves: u32) -> f64 {
+        self.octaves(p, octaves, f64::abs)
+    }
+
+    fn octaves(&self, p: &Point, octaves: u32, fold: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total_weight = 0.0;
+        let mut weight = 1.0;
+        let mut p = *p;
+        for _ in 0..octaves {
+            sum += weight * fold(self.noise(&p));
+            total_weight += weight;
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        if total_weight > 0.0 {
+            sum / total_weight
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_smooth_and_seeded() {
+        let perlin = Perlin::new(7);
+        assert_eq!(perlin.noise(&Point::new(3.0, -2.0, 5.0)), 0.0);
+
+        let p = Point::new(1.3, 0.7, -2.2);
+        let step = Point::new(1e-4, 1e-4, 1e-4);
+        assert!((perlin.noise(&p) - perlin.noise(&(p + step))).abs() < 1e-3);
+
+        // Same seed, same noise, but another one differs
+        assert_eq!(perlin.noise(&p), Perlin::new(7).noise(&p));
+        assert_ne!(perlin.noise(&p), Perlin::new(8).noise(&p));
+    }
+
+    #[test]
+    fn octaves_stay_in_range() {
+        let perlin = Perlin::new(0);
+        for i in 0..1000 {
+            let p = Point::new(i as f64 * 0.37, i as f64 * -0.11, i as f64 * 0.05);
+            assert!((-1.0..=1.0).contains(&perlin.fbm(&p, 6)));
+            assert!((0.0..=1.0).contains(&perlin.turbulence(&p, 6)));
+        }
+    }
+}
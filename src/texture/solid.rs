@@ -0,0 +1,20 @@
+use super::Texture;
+use crate::{Color, Point};
+
+/// The same color everywhere
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
+        self.color
+    }
+}
@@ -0,0 +1,219 @@
+use super::{Aabb, Point};
+
+type Matrix = [[f64; 4]; 4];
+
+/// An affine transformation as a 4x4 matrix
+///
+/// The inverse is built together with the matrix,
+/// so it never has to be computed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    pub fn translate(offset: &Point) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for i in 0..3 {
+            matrix[i][3] = offset[i];
+            inverse[i][3] = -offset[i];
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Scale along the axes, none of the factors can be zero
+    pub fn scale(factors: &Point) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for i in 0..3 {
+            matrix[i][i] = factors[i];
+            inverse[i][i] = 1.0 / factors[i];
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Rotate counter-clockwise around an axis going through the origin
+    pub fn rotate(axis: &Point, degrees: f64) -> Self {
+        // Rodrigues' rotation formula
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        let matrix = [
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // The inverse of a rotation is its transpose
+        let mut inverse = IDENTITY;
+        for (i, row) in inverse.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = matrix[j][i];
+            }
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Apply this transformation, then the other one
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            matrix: multiply(&other.matrix, &self.matrix),
+            inverse: multiply(&self.inverse, &other.inverse),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, p: &Point) -> Point {
+        let m = &self.matrix;
+        let row = |i: usize| m[i][0] * p.x() + m[i][1] * p.y() + m[i][2] * p.z() + m[i][3];
+        Point::new(row(0), row(1), row(2))
+    }
+
+    /// Directions are not moved by the translation
+    pub fn vector(&self, v: &Point) -> Point {
+        let m = &self.matrix;
+        let row = |i: usize| m[i][0] * v.x() + m[i][1] * v.y() + m[i][2] * v.z();
+        Point::new(row(0), row(1), row(2))
+    }
+
+    /// Normals stay perpendicular to the surface with the inverse transpose
+    ///
+    /// The result is not a unit vector
+    pub fn normal(&self, n: &Point) -> Point {
+        let m = &self.inverse;
+        let column = |i: usize| m[0][i] * n.x() + m[1][i] * n.y() + m[2][i] * n.z();
+        Point::new(column(0), column(1), column(2))
+    }
+
+    /// How much the volumes grow, negative if the transformation mirrors
+    pub fn determinant(&self) -> f64 {
+        let m = &self.matrix;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// The box around the transformed corners of the box
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let (min, max) = (*bbox.min(), *bbox.max());
+        let corners: Vec<Point> = (0..8)
+            .map(|i| {
+                let pick = |axis: usize| {
+                    if i & (1 << axis) == 0 {
+                        min[axis]
+                    } else {
+                        max[axis]
+                    }
+                };
+                self.point(&Point::new(pick(0), pick(1), pick(2)))
+            })
+            .collect();
+        Aabb::from_points(&corners)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Point, b: Point) {
+        assert!((a - b).near_zero(), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn scale_rotate_translate() {
+        let transform = Transform::scale(&Point::new(2.0, 2.0, 2.0))
+            .then(&Transform::rotate(&Point::new(0.0, 0.0, 1.0), 90.0))
+            .then(&Transform::translate(&Point::new(1.0, 0.0, 0.0)));
+
+        let p = Point::new(1.0, 0.0, 0.0);
+        assert_near(transform.point(&p), Point::new(1.0, 2.0, 0.0));
+        assert_near(transform.vector(&p), Point::new(0.0, 2.0, 0.0));
+        assert_near(transform.inverse().point(&transform.point(&p)), p);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let transform = Transform::scale(&Point::new(1.0, 4.0, 1.0))
+            .then(&Transform::rotate(&Point::new(1.0, 1.0, 0.0), 30.0));
+
+        // A surface along (1, 1, 0) with normal (1, -1, 0)
+        let tangent = transform.vector(&Point::new(1.0, 1.0, 0.0));
+        let normal = transform.normal(&Point::new(1.0, -1.0, 0.0));
+        assert!(Point::dot(&tangent, &normal).abs() < 1e-12);
+
+        // The rotation keeps the volumes
+        assert!((transform.determinant() - 4.0).abs() < 1e-12);
+        assert!((transform.inverse().determinant() - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bounding_box_of_rotated_box() {
+        let bbox = Aabb::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let rotated = Transform::rotate(&Point::new(0.0, 1.0, 0.0), 45.0).bounding_box(&bbox);
+        let half_diagonal = 2.0f64.sqrt();
+        assert_near(
+            *rotated.min(),
+            Point::new(-half_diagonal, -1.0, -half_diagonal),
+        );
+        assert_near(
+            *rotated.max(),
+            Point::new(half_diagonal, 1.0, half_diagonal),
+        );
+    }
+}
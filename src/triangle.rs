@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use super::{Aabb, HitRecord, Hittable, Point, Ray, Sampler};
+use crate::material::Material;
+
+/// A flat triangle given by its three corners
+///
+/// The front face is the one where the corners are in counter-clockwise order
+#[derive(Debug)]
+pub struct Triangle {
+    vertices: [Point; 3],
+    material: Arc<dyn Material>,
+    /// Given to the hit records
+    id: u32,
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            material,
+            id: 0,
+        }
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+}
+
+/// Where the ray hits the triangle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleHit {
+    pub t: f64,
+    /// Barycentric weight of the second vertex
+    pub b1: f64,
+    /// Barycentric weight of the third vertex
+    pub b2: f64,
+}
+
+impl TriangleHit {
+    /// Barycentric weight of the first vertex
+    pub fn b0(&self) -> f64 {
+        1.0 - self.b1 - self.b2
+    }
+}
+
+/// The Möller–Trumbore algorithm
+///
+/// The hit point is written as `(1 - b1 - b2) * v0 + b1 * v1 + b2 * v2`
+/// and solved together with the ray's equation using Cramer's rule.
+/// The ray is inside the triangle if all three weights are non-negative.
+pub fn intersect(r: &Ray, vertices: &[Point; 3], t_min: f64, t_max: f64) -> Option<TriangleHit> {
+    let [v0, v1, v2] = vertices;
+    let edge1 = *v1 - *v0;
+    let edge2 = *v2 - *v0;
+
+    let p = Point::cross(r.direction(), &edge2);
+    let determinant = Point::dot(&edge1, &p);
+
+    // The ray is parallel to the triangle
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inv_determinant = 1.0 / determinant;
+
+    let to_origin = *r.origin() - *v0;
+    let b1 = Point::dot(&to_origin, &p) * inv_determinant;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q = Point::cross(&to_origin, &edge1);
+    let b2 = Point::dot(r.direction(), &q) * inv_determinant;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = Point::dot(&edge2, &q) * inv_determinant;
+    if t_min < t && t < t_max {
+        Some(TriangleHit { t, b1, b2 })
+    } else {
+        None
+    }
+}
+
+/// Normal vector of the counter-clockwise face
+pub fn face_normal(vertices: &[Point; 3]) -> Point {
+    let [v0, v1, v2] = vertices;
+    Point::cross(&(*v1 - *v0), &(*v2 - *v0)).unit_vector()
+}
+
+fn area(vertices: &[Point; 3]) -> f64 {
+    let [v0, v1, v2] = vertices;
+    Point::cross(&(*v1 - *v0), &(*v2 - *v0)).len() / 2.0
+}
+
+/// The probability density (per solid angle) of `random_towards` choosing `direction`
+///
+/// The area density is converted to solid angle by `distance^2 / cos`
+pub fn solid_angle_pdf(vertices: &[Point; 3], origin: &Point, direction: &Point) -> f64 {
+    let ray = Ray::new(*origin, *direction);
+    let hit = match intersect(&ray, vertices, 0.001, f64::INFINITY) {
+        Some(hit) => hit,
+        None => return 0.0,
+    };
+    let distance_squared = hit.t.powi(2) * direction.len_squared();
+    let cosine = Point::dot(direction, &face_normal(vertices)).abs() / direction.len();
+    if cosine < 1e-12 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area(vertices))
+}
+
+/// Direction from `origin` to a uniformly chosen point of the triangle
+pub fn random_towards(vertices: &[Point; 3], origin: &Point, sampler: &mut Sampler) -> Point {
+    let [v0, v1, v2] = vertices;
+    // The square root keeps the points uniform over the area
+    let s = sampler.next_f64().sqrt();
+    let b1 = sampler.next_f64() * s;
+    let b0 = 1.0 - s;
+    let b2 = 1.0 - b0 - b1;
+    b0 * *v0 + b1 * *v1 + b2 * *v2 - *origin
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let hit = intersect(r, &self.vertices, t_min, t_max)?;
+        let mut result = HitRecord {
+            t: hit.t,
+            position: r.point_at(hit.t).unwrap(),
+            material: self.material.clone(),
+            // The corners are at (0, 0), (1, 0) and (0, 1)
+            u: hit.b1,
+            v: hit.b2,
+            object_id: self.id,
+            front_face: false,        // by set_front_face
+            normal: Point::default(), // by set_front_face
+        };
+        result.set_front_face(r, &face_normal(&self.vertices));
+        Some(result)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&self.vertices))
+    }
+
+    fn pdf_value(&self, origin: &Point, direction: &Point, _time: f64) -> f64 {
+        solid_angle_pdf(&self.vertices, origin, direction)
+    }
+
+    fn random(&self, origin: &Point, _time: f64, sampler: &mut Sampler) -> Option<Point> {
+        Some(random_towards(&self.vertices, origin, sampler))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, Color};
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point::new(-1.0, -1.0, -1.0),
+            Point::new(1.0, -1.0, -1.0),
+            Point::new(0.0, 1.0, -1.0),
+            Arc::new(Lambertian::new(Color::white())),
+        )
+    }
+
+    #[test]
+    fn hit_front_face() {
+        let ray = Ray::new(Point::default(), Point::new(0.0, 0.0, -1.0));
+        let hit = triangle().hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Point::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hit_back_face() {
+        let ray = Ray::new(Point::new(0.0, 0.0, -2.0), Point::new(0.0, 0.0, 1.0));
+        let hit = triangle().hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Point::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn miss_outside() {
+        let ray = Ray::new(Point::default(), Point::new(1.0, 1.0, -1.0));
+        assert!(triangle().hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn barycentric_weights() {
+        let vertices = triangle().vertices;
+        let ray = Ray::new(Point::new(1.0, -1.0, 0.0), Point::new(0.0, 0.0, -1.0));
+        let hit = intersect(&ray, &vertices, 0.001, f64::INFINITY).unwrap();
+        assert!((hit.b1 - 1.0).abs() < 1e-9);
+        assert!(hit.b2.abs() < 1e-9);
+        assert!(hit.b0().abs() < 1e-9);
+    }
+
+    #[test]
+    fn sampling_covers_the_solid_angle() {
+        let triangle = triangle();
+        let origin = Point::new(0.2, 0.3, 1.0);
+
+        // Van Oosterom and Strackee
+        let [a, b, c] = triangle.vertices.map(|v| v - origin);
+        let (la, lb, lc) = (a.len(), b.len(), c.len());
+        let numerator = Point::dot(&a, &Point::cross(&b, &c)).abs();
+        let denominator = la * lb * lc
+            + Point::dot(&a, &b) * lc
+            + Point::dot(&a, &c) * lb
+            + Point::dot(&b, &c) * la;
+        let solid_angle = 2.0 * numerator.atan2(denominator);
+
+        // The average of 1 / pdf estimates the solid angle
+        let samples = 20_000;
+        let mut sampler = Sampler::new(0, 0, 0);
+        let estimate: f64 = (0..samples)
+            .map(|_| {
+                1.0 / triangle.pdf_value(
+                    &origin,
+                    &triangle.random(&origin, 0.0, &mut sampler).unwrap(),
+                    0.0,
+                )
+            })
+            .sum::<f64>()
+            / samples as f64;
+        assert!((estimate - solid_angle).abs() < 0.02 * solid_angle);
+
+        let away = Point::new(0.0, 0.0, 1.0);
+        assert_eq!(triangle.pdf_value(&origin, &away, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,52 @@
+use super::{Environment, Hittable, HittableList, Point, Sampler};
+
+/// Everything a ray can interact with
+#[derive(Debug)]
+pub struct World {
+    /// All the objects, usually in a BVH
+    pub objects: Box<dyn Hittable>,
+    /// The emissive objects again, shadow rays are sent towards them
+    pub lights: HittableList,
+    /// The light from where the rays don't hit anything
+    pub environment: Box<dyn Environment>,
+}
+
+impl World {
+    /// The chance of a shadow ray going towards the lights instead of the environment
+    fn lights_weight(&self) -> f64 {
+        match (self.lights.is_empty(), self.environment.is_sampled()) {
+            (false, true) => 0.5,
+            (false, false) => 1.0,
+            (true, _) => 0.0,
+        }
+    }
+
+    /// Whether shadow rays can be sent anywhere
+    pub fn has_lights(&self) -> bool {
+        !self.lights.is_empty() || self.environment.is_sampled()
+    }
+
+    /// Density of `random_light` choosing the direction
+    pub fn light_pdf(&self, origin: &Point, direction: &Point, time: f64) -> f64 {
+        let weight = self.lights_weight();
+        let mut pdf = 0.0;
+        if weight > 0.0 {
+            pdf += weight * self.lights.pdf_value(origin, direction, time);
+        }
+        if weight < 1.0 && self.environment.is_sampled() {
+            pdf += (1.0 - weight) * self.environment.pdf_value(direction);
+        }
+        pdf
+    }
+
+    /// A direction of a shadow ray at `time`, either towards the lights or the environment
+    ///
+    /// `None` if the chosen light can't be sampled from `origin`
+    pub fn random_light(&self, origin: &Point, time: f64, sampler: &mut Sampler) -> Option<Point> {
+        if sampler.next_f64() < self.lights_weight() {
+            self.lights.random(origin, time, sampler)
+        } else {
+            self.environment.random(sampler)
+        }
+    }
+}